@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A parsed JSON value, just enough to read back the checklist's own JSON files.
+pub enum Value {
+    Null,
+    /// true or false, which no task field holds, so only its presence is kept.
+    Bool,
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.get(key),
+            _ => None,
+        }
+    }
+}
+
+/// Quotes `text` as a JSON string.
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats already encoded `fields` as a single line JSON object.
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}: {}", quote(key), value)).collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Formats already encoded `items` as a JSON array with one item per line.
+pub fn array(items: &[String]) -> String {
+    if items.is_empty() {
+        return "[]".to_string();
+    }
+
+    let items: Vec<String> = items.iter().map(|item| format!("  {}", item)).collect();
+    format!("[\n{}\n]", items.join(",\n"))
+}
+
+/// Deepest nesting of arrays and objects accepted, so a malformed file cannot overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars, 0)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        Some(c) => Err(format!("unexpected '{}' after JSON value", c)),
+        None => Ok(value),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while let Some(c) = chars.peek() {
+        if !c.is_whitespace() {
+            break;
+        }
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: &str) -> Result<(), String> {
+    for e in expected.chars() {
+        if chars.next() != Some(e) {
+            return Err(format!("invalid JSON, expected \"{}\"", expected));
+        }
+    }
+    Ok(())
+}
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Value, String> {
+    skip_whitespace(chars);
+    if depth > MAX_DEPTH && matches!(chars.peek(), Some('[' | '{')) {
+        return Err(format!("JSON is nested deeper than {} levels", MAX_DEPTH));
+    }
+    match chars.peek() {
+        Some('n') => expect(chars, "null").map(|_| Value::Null),
+        Some('t') => expect(chars, "true").map(|_| Value::Bool),
+        Some('f') => expect(chars, "false").map(|_| Value::Bool),
+        Some('"') => parse_string(chars).map(Value::String),
+        Some('[') => {
+            chars.next();
+            let mut items = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Value::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Value::Array(items)),
+                    _ => return Err("invalid JSON, expected ',' or ']'".to_string()),
+                }
+            }
+        },
+        Some('{') => {
+            chars.next();
+            let mut fields = BTreeMap::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Value::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                expect(chars, ":")?;
+                fields.insert(key, parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Value::Object(fields)),
+                    _ => return Err("invalid JSON, expected ',' or '}'".to_string()),
+                }
+            }
+        },
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(c) = chars.peek() {
+                if !(c.is_ascii_digit() || "+-.eE".contains(*c)) {
+                    break;
+                }
+                number.push(*c);
+                chars.next();
+            }
+            match number.parse::<f64>() {
+                Ok(number) => Ok(Value::Number(number)),
+                Err(_) => Err(format!("invalid JSON number \"{}\"", number)),
+            }
+        },
+        Some(c) => Err(format!("unexpected '{}' in JSON", c)),
+        None => Err("unexpected end of JSON".to_string()),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, "\"")?;
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('"') => text.push('"'),
+                Some('\\') => text.push('\\'),
+                Some('/') => text.push('/'),
+                Some('b') => text.push('\u{8}'),
+                Some('f') => text.push('\u{c}'),
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('u') => {
+                    let unit = parse_unit(chars)?;
+                    // characters outside the basic plane are escaped as a UTF-16 surrogate pair
+                    let code = if (0xD800..0xDC00).contains(&unit) {
+                        let low = match (chars.next(), chars.next()) {
+                            (Some('\\'), Some('u')) => parse_unit(chars)?,
+                            _ => return Err(format!("invalid JSON escape \\u{:04x} without low surrogate", unit)),
+                        };
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err(format!("invalid JSON escape \\u{:04x}\\u{:04x}", unit, low));
+                        }
+                        0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+                    } else {
+                        unit
+                    };
+                    match char::from_u32(code) {
+                        Some(c) => text.push(c),
+                        None => return Err(format!("invalid JSON escape \\u{:04x}", code)),
+                    }
+                },
+                _ => return Err("invalid JSON escape".to_string()),
+            },
+            Some(c) => text.push(c),
+            None => return Err("unterminated JSON string".to_string()),
+        }
+    }
+}
+
+/// Reads the four hex digits of a `\u` escape.
+fn parse_unit(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let hex: String = chars.by_ref().take(4).collect();
+    match u32::from_str_radix(&hex, 16) {
+        Ok(unit) if hex.len() == 4 => Ok(unit),
+        _ => Err(format!("invalid JSON escape \\u{}", hex)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> String {
+        match parse(text) {
+            Ok(Value::String(text)) => text,
+            _ => panic!("{} is not a JSON string", text),
+        }
+    }
+
+    #[test]
+    fn surrogate_pair_escape() {
+        assert_eq!(string(r#""Emoji \ud83d\ude00""#), "Emoji 😀");
+        assert_eq!(string(r#""\u00e7""#), "ç");
+        assert!(parse(r#""\ud83d""#).is_err());
+        assert!(parse(r#""\ud83d\u0041""#).is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        assert!(parse(&format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1))).is_ok());
+        assert!(parse(&"[".repeat(100_000)).is_err());
+    }
+}
@@ -1,93 +1,1024 @@
+mod json;
+mod yaml;
+
 use std::env;
+use std::fmt;
 use std::fs;
-use std::io::Error;
+use std::io::{self, Error, ErrorKind, IsTerminal, Read, Write};
 use std::string::ToString;
 use std::cmp;
-use chrono::{Local, NaiveDate, Duration};
-use colored::Colorize;
+use std::hint;
+use std::mem;
+use std::path::Path;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use chrono::{Datelike, Local, Months, NaiveDate, Duration, Weekday};
+use colored::{ColoredString, Colorize};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use once_cell::sync::Lazy;
+
+/// Whether debug logging to stderr was requested with `CHECKLIST_LOG=debug`.
+static DEBUG: Lazy<bool> = Lazy::new(|| {
+    matches!(env::var("CHECKLIST_LOG"), Ok(level) if level == "debug")
+});
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if *DEBUG {
+            eprintln!("[debug] {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Why a command or library call failed. It displays as the message the command line prints.
+#[derive(Debug)]
+pub enum ChecklistError {
+    /// No task has the given name or `#id`, among the tasks due on the date if one is given.
+    TaskNotFound(String, Option<NaiveDate>),
+    /// A task with the given name exists already.
+    DuplicateTask(String),
+    Io(io::Error),
+    /// A date, interval or other value, or a line of the checklist, cannot be read.
+    Parse(String),
+    NotEnoughArgs,
+    /// Not a failure, the command reports its result through the exit code, like status --code.
+    Exit(u8),
+    Other(String),
+}
+
+impl fmt::Display for ChecklistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChecklistError::TaskNotFound(reference, Some(due_date)) => {
+                write!(f, "cannot find task \"{}\" due on {}", reference, due_date)
+            },
+            ChecklistError::TaskNotFound(reference, None) if is_id_reference(reference) => {
+                write!(f, "cannot find task {}", reference)
+            },
+            ChecklistError::TaskNotFound(reference, None) => write!(f, "cannot find task named \"{}\"", reference),
+            ChecklistError::DuplicateTask(task_name) => write!(f, "entry with name {} already exists", task_name),
+            ChecklistError::Io(e) => write!(f, "{}", e),
+            ChecklistError::Parse(message) | ChecklistError::Other(message) => write!(f, "{}", message),
+            ChecklistError::NotEnoughArgs => write!(f, "not enough parameters"),
+            ChecklistError::Exit(code) => write!(f, "exit code {}", code),
+        }
+    }
+}
+
+impl std::error::Error for ChecklistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChecklistError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
-#[derive(Clone)]
+impl ChecklistError {
+    /// An I/O error on `path`, naming the file in its message.
+    fn io(path: &str, e: io::Error) -> ChecklistError {
+        ChecklistError::Io(io::Error::new(e.kind(), format!("{}: {}", path, e)))
+    }
+}
+
+impl From<io::Error> for ChecklistError {
+    fn from(e: io::Error) -> ChecklistError {
+        ChecklistError::Io(e)
+    }
+}
+
+/// Prints a message of a command that changes the checklist. It goes to stderr when the
+/// checklist is piped through stdout, so the next command only reads the checklist.
+macro_rules! say {
+    ($config:expr, $($arg:tt)*) => {
+        if $config.file_path == STDIO_PATH {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Prints a confirmation of what a command changed unless --quiet or --dry-run was given.
+macro_rules! report {
+    ($config:expr, $($arg:tt)*) => {
+        if !$config.quiet && !$config.dry_run {
+            say!($config, $($arg)*);
+        }
+    };
+}
+
+#[derive(Clone, Debug)]
 pub struct Config {
     file_path: String,
     args: Vec<String>,
+    add_position: AddPosition,
+    max_tasks: Option<usize>,
+    show_path: bool,
+    page_threshold: Option<usize>,
+    markers: Option<(String, String)>,
+    no_recurse: bool,
+    dedupe_on_load: bool,
+    assume_today: Option<NaiveDate>,
+    show_summary: bool,
+    dry_run: bool,
+    /// Whether output is colored, decided by --color, NO_COLOR and whether stdout is a terminal.
+    color: bool,
+    quiet: bool,
+}
+
+/// Checklist path that reads the checklist from stdin and writes it to stdout instead.
+const STDIO_PATH: &str = "-";
+
+/// The checklist piped in when the path is `STDIO_PATH`, read once since stdin cannot be rewound.
+static STDIN_CHECKLIST: Lazy<Result<String, String>> = Lazy::new(|| {
+    let mut content = String::new();
+    match io::stdin().read_to_string(&mut content) {
+        Ok(_) => Ok(content),
+        Err(e) => Err(e.to_string()),
+    }
+});
+
+/// How long a command waits for another one to release the checklist.
+const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Whether the checklist was written to stdout, so a piped checklist is passed on only once.
+static STDOUT_WRITTEN: AtomicBool = AtomicBool::new(false);
+
+/// Advisory lock held while a command reads, changes and writes the checklist.
+/// It is a `<file>.lock` file next to the checklist that is removed when dropped.
+/// A checklist piped through stdin and stdout has no file to lock. Instead, a command that
+/// wrote nothing passes it on unchanged when the lock is dropped, so the next command
+/// still gets the checklist.
+#[derive(Debug)]
+struct Lock {
+    path: Option<String>,
+}
+
+impl Lock {
+    fn acquire(file_path: &str) -> Result<Lock, ChecklistError> {
+        if file_path == STDIO_PATH {
+            return Ok(Lock { path: None });
+        }
+
+        let path = format!("{}.lock", file_path);
+        let start = Instant::now();
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Lock { path: Some(path) }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && start.elapsed() < LOCK_TIMEOUT => {
+                    thread::sleep(std::time::Duration::from_millis(50));
+                },
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    return Err(ChecklistError::Other(format!("checklist is busy, delete {} if no other checklist command is running", path)));
+                },
+                Err(e) => return Err(ChecklistError::io(&path, e)),
+            }
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        match &self.path {
+            Some(path) => {
+                let _ = fs::remove_file(path);
+            },
+            None => if !STDOUT_WRITTEN.load(Ordering::Relaxed) {
+                if let Ok(content) = &*STDIN_CHECKLIST {
+                    let mut stdout = io::stdout();
+                    let _ = stdout.write_all(content.as_bytes()).and_then(|_| stdout.flush());
+                }
+            },
+        }
+    }
+}
+
+/// Where `add` inserts a new entry into the checklist file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AddPosition {
+    Top,
+    Bottom,
+    Sorted,
+}
+
+impl AddPosition {
+    fn parse(position: &str) -> Result<AddPosition, ChecklistError> {
+        match position {
+            "top"       => Ok(AddPosition::Top),
+            "bottom"    => Ok(AddPosition::Bottom),
+            "sorted"    => Ok(AddPosition::Sorted),
+            _           => Err(ChecklistError::Parse(format!("invalid add position \"{}\", expected top, bottom or sorted", position))),
+        }
+    }
 }
 
 impl Config {
+    /// Fails if adding `additional` tasks to a checklist of `count` tasks would exceed `max_tasks`.
+    fn check_max_tasks(&self, count: usize, additional: usize) -> Result<(), ChecklistError> {
+        match self.max_tasks {
+            Some(max_tasks) if count + additional > max_tasks => Err(ChecklistError::Other(format!(
+                "checklist would exceed the limit of {} tasks, raise CHECKLIST_MAX_TASKS or unset it to add more",
+                max_tasks
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The date commands treat as today, which --assume-today overrides.
+    fn today(&self) -> NaiveDate {
+        self.assume_today.unwrap_or_else(|| Local::now().date_naive())
+    }
+
+    /// Writes `table` to the checklist, or with --dry-run prints how it differs from the
+    /// checklist and writes nothing.
+    fn save(&self, table: &TaskTable) -> Result<(), ChecklistError> {
+        if !self.dry_run {
+            return table.save(&self.file_path);
+        }
+
+        let current = TaskTable::load(&self.file_path)?;
+        for line in current.diff(table).lines() {
+            say!(self, "{}", line);
+        }
+        say!(self, "{}", "dry run, nothing was written".dimmed());
+        Ok(())
+    }
+
+    /// Locks the checklist against other commands changing it until the lock is dropped.
+    fn lock(&self) -> Result<Lock, ChecklistError> {
+        Lock::acquire(&self.file_path)
+    }
+
+    /// Appends checked tasks to the completion log unless this is a dry run.
+    fn record_history(&self, task_names: &[&String], date: NaiveDate) -> Result<(), ChecklistError> {
+        if self.dry_run || self.file_path == STDIO_PATH {
+            return Ok(());
+        }
+        append_history(&self.file_path, task_names, date)
+    }
+
+    /// Turns colored output on or off for every command as decided by --color.
+    pub fn apply_color(&self) {
+        colored::control::set_override(self.color);
+    }
+
+    /// Prints "N overdue, M due today" to stderr if CHECKLIST_SHOW_SUMMARY is set,
+    /// unless the command's output is meant for other programs.
+    pub fn print_summary(&self) {
+        const MACHINE_FLAGS: [&str; 4] = ["--json", "--json-computed", "--format", "--code"];
+        if !self.show_summary || self.args.iter().any(|arg| MACHINE_FLAGS.contains(&arg.as_str())) {
+            return;
+        }
+
+        // a missing or broken checklist is reported by the command itself
+        if let Ok(table) = TaskTable::load(&self.file_path) {
+            let today = self.today();
+            let visible: Vec<&TaskEntry> = table.tasks.iter().filter(|entry| !entry.is_hidden(today)).collect();
+            let overdue = visible.iter().filter(|entry| entry.due_date < today).count();
+            let due_today = visible.iter().filter(|entry| entry.due_date == today).count();
+            eprintln!("{}", format!("{} overdue, {} due today", overdue, due_today).dimmed());
+        }
+    }
+
+    /// Loads the checklist for commands that only read it, dropping duplicates if asked to.
+    /// Lines that cannot be read are skipped with a warning, since nothing is written back.
+    fn load_for_reading(&self) -> Result<TaskTable, ChecklistError> {
+        let content = match read_checklist(&self.file_path) {
+            Ok(content) => content,
+            Err(e) => return Err(ChecklistError::io(&self.file_path, e)),
+        };
+        // a JSON checklist has no lines to skip, it either loads or not
+        let (mut table, errors) = if is_json(&self.file_path) {
+            (TaskTable::parse(&self.file_path, &content)?, vec![])
+        } else {
+            TaskTable::deserialize_lenient(&content)
+        };
+        for error in &errors {
+            eprintln!("warning: skipping {}", error);
+        }
+        if self.dedupe_on_load {
+            let duplicates = table.dedupe();
+            if duplicates > 0 {
+                eprintln!("warning: ignoring {} duplicate tasks, run dedupe to remove them", duplicates);
+            }
+        }
+        Ok(table)
+    }
+
     pub fn build(mut args: Vec<String>) -> Result<Config, &'static str> {
         
-        let file_path = match env::var("CHECKLIST_FILE") {
-            Ok(var) => var,
-            Err(msg) => panic!("{msg}"),
+        let file_var = env::var("CHECKLIST_FILE").ok();
+
+        let add_position = match env::var("CHECKLIST_ADD_POSITION") {
+            Ok(var) => match AddPosition::parse(&var) {
+                Ok(position) => position,
+                Err(_) => return Err("CHECKLIST_ADD_POSITION must be top, bottom or sorted"),
+            },
+            Err(_) => AddPosition::Top,
+        };
+
+        let max_tasks = match env::var("CHECKLIST_MAX_TASKS") {
+            Ok(var) => match var.parse::<usize>() {
+                Ok(max_tasks) => Some(max_tasks),
+                Err(_) => return Err("CHECKLIST_MAX_TASKS must be a whole number"),
+            },
+            Err(_) => None,
+        };
+
+        let page_threshold = match env::var("CHECKLIST_PAGE_THRESHOLD") {
+            Ok(var) => match var.parse::<usize>() {
+                Ok(page_threshold) => Some(page_threshold),
+                Err(_) => return Err("CHECKLIST_PAGE_THRESHOLD must be a whole number"),
+            },
+            Err(_) => None,
+        };
+
+        let markers = match env::var("CHECKLIST_MARKERS") {
+            Ok(var) => match var.split_once(',') {
+                Some((overdue, today)) => Some((overdue.to_string(), today.to_string())),
+                None => return Err("CHECKLIST_MARKERS must be two symbols separated by a comma"),
+            },
+            Err(_) => None,
         };
 
+        let show_path = matches!(env::var("CHECKLIST_SHOW_PATH"), Ok(var) if var == "1");
+        let show_summary = matches!(env::var("CHECKLIST_SHOW_SUMMARY"), Ok(var) if var == "1");
+
         args = args.drain(2..).collect();
 
+        // --file takes precedence over CHECKLIST_FILE
+        let mut file_path = match take_option(&mut args, "--file") {
+            Ok(Some(file_path)) => file_path,
+            Ok(None) => match file_var {
+                Some(file_path) => file_path,
+                None => return Err("no checklist file, pass --file or set CHECKLIST_FILE"),
+            },
+            Err(_) => return Err("missing value for --file"),
+        };
+
+        let no_recurse = take_flag(&mut args, "--no-recurse");
+        let quiet = take_flag(&mut args, "--quiet");
+        let dry_run = take_flag(&mut args, "--dry-run");
+        let dedupe_on_load = take_flag(&mut args, "--dedupe-on-load")
+            || matches!(env::var("CHECKLIST_DEDUPE_ON_LOAD"), Ok(var) if var == "1");
+        let assume_today = match take_option(&mut args, "--assume-today") {
+            Ok(Some(date)) => match parse_date(&date, Local::now().date_naive()) {
+                Ok(date) => Some(date),
+                Err(_) => return Err("--assume-today must be YYYY-MM-DD, today, tomorrow, yesterday, +N or a weekday"),
+            },
+            Ok(None) => None,
+            Err(_) => return Err("missing value for --assume-today"),
+        };
+        // named lists live next to CHECKLIST_FILE
+        match take_option(&mut args, "--list") {
+            Ok(Some(name)) => {
+                if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+                    return Err("--list must be a plain file name");
+                }
+                let directory = Path::new(&file_path).parent().unwrap_or(Path::new(""));
+                file_path = directory.join(name).to_string_lossy().into_owned();
+            },
+            Ok(None) => {},
+            Err(_) => return Err("missing value for --list"),
+        }
+        // --color=never is accepted as well as --color never
+        let color = match args.iter().position(|arg| arg.starts_with("--color=")) {
+            Some(index) => Some(args.remove(index)["--color=".len()..].to_string()),
+            None => match take_option(&mut args, "--color") {
+                Ok(color) => color,
+                Err(_) => return Err("missing value for --color"),
+            },
+        };
+        let color = match color.as_deref() {
+            Some("always") => true,
+            Some("never") => false,
+            Some("auto") | None => {
+                env::var_os("NO_COLOR").is_none_or(|var| var.is_empty()) && io::stdout().is_terminal()
+            },
+            Some(_) => return Err("--color must be always, never or auto"),
+        };
+
+        debug!("checklist file {}", file_path);
+        debug!("arguments {:?}", args);
+
         Ok(Config {
             file_path,
             args,
+            add_position,
+            max_tasks,
+            show_path,
+            page_threshold,
+            markers,
+            no_recurse,
+            dedupe_on_load,
+            assume_today,
+            show_summary,
+            dry_run,
+            color,
+            quiet,
         })
     }
 }
 
-struct TaskEntry {
+/// What `check` advances a recurring task's due date from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    /// The day the task was checked, so late completions push the schedule back.
+    Last,
+    /// The previous due date, so the schedule never drifts.
+    Due,
+}
+
+impl Anchor {
+    fn parse(anchor: &str) -> Result<Anchor, ChecklistError> {
+        match anchor {
+            "last"      => Ok(Anchor::Last),
+            "due"       => Ok(Anchor::Due),
+            _           => Err(ChecklistError::Parse(format!("invalid anchor \"{}\", expected last or due", anchor))),
+        }
+    }
+
+    fn serialize(&self) -> &'static str {
+        match self {
+            Anchor::Last => "last",
+            Anchor::Due => "due",
+        }
+    }
+}
+
+/// The full English name of `weekday`, e.g. "Monday".
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// Longest interval accepted, a hundred years, so due dates stay far from chrono's limits.
+const MAX_INTERVAL_DAYS: u32 = 36_500;
+
+/// How often a task recurs once it is checked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interval {
+    /// Every given number of days, zero meaning the task happens once.
+    Days(u32),
+    /// Every given number of weeks.
+    Weeks(u32),
+    /// Every given number of calendar months, staying on the last day for short months.
+    Months(u32),
+    /// On the next such weekday, a full week later when checked on that day.
+    Weekday(Weekday),
+    /// On the last day of the month.
+    MonthEnd,
+    /// On the last weekday of the month.
+    MonthEndBusiness,
+}
+
+impl Interval {
+    fn parse(interval: &str) -> Result<Interval, ChecklistError> {
+        match interval {
+            "once"                  => Ok(Interval::Days(0)),
+            "month-end"             => Ok(Interval::MonthEnd),
+            "month-end-business"    => Ok(Interval::MonthEndBusiness),
+            _ if interval.ends_with('w') || interval.ends_with("mo") => {
+                let (count, weeks) = match interval.strip_suffix('w') {
+                    Some(count) => (count, true),
+                    None => (interval.strip_suffix("mo").unwrap_or(interval), false),
+                };
+                match count.parse::<u32>() {
+                    Ok(0) => Err(ChecklistError::Parse("interval must be at least 1w or 1mo, use once for one-time tasks".to_string())),
+                    Ok(count) if weeks && count <= MAX_INTERVAL_DAYS / 7 => Ok(Interval::Weeks(count)),
+                    Ok(count) if !weeks && count <= MAX_INTERVAL_DAYS / 30 => Ok(Interval::Months(count)),
+                    Ok(_) => Err(ChecklistError::Parse(format!("interval must be at most {} days", MAX_INTERVAL_DAYS))),
+                    Err(_) => Err(ChecklistError::Parse(format!("invalid interval \"{}\", expected a number of weeks like 2w or months like 1mo", interval))),
+                }
+            },
+            _ => match interval.parse::<u32>() {
+                Ok(days) if days <= MAX_INTERVAL_DAYS => Ok(Interval::Days(days)),
+                Ok(_) => Err(ChecklistError::Parse(format!("interval must be at most {} days", MAX_INTERVAL_DAYS))),
+                Err(_) => match interval.parse::<Weekday>() {
+                    Ok(weekday) => Ok(Interval::Weekday(weekday)),
+                    Err(_) => Err(ChecklistError::Parse(format!(
+                        "invalid interval \"{}\", interval must be a non-negative whole number of days, Nw, Nmo, a weekday, once, month-end or month-end-business",
+                        interval
+                    ))),
+                },
+            },
+        }
+    }
+
+    fn serialize(&self) -> String {
+        match self {
+            Interval::Days(days) => days.to_string(),
+            Interval::Weeks(weeks) => format!("{}w", weeks),
+            Interval::Months(months) => format!("{}mo", months),
+            Interval::Weekday(weekday) => weekday_name(*weekday).to_lowercase(),
+            Interval::MonthEnd => "month-end".to_string(),
+            Interval::MonthEndBusiness => "month-end-business".to_string(),
+        }
+    }
+
+    /// The interval as shown in tables.
+    fn display(&self) -> String {
+        match self {
+            Interval::Days(0) => "once".to_string(),
+            Interval::MonthEndBusiness => "month-end (business)".to_string(),
+            Interval::Weekday(weekday) => format!("weekly ({})", weekday),
+            _ => self.serialize(),
+        }
+    }
+
+    /// Orders intervals from the most to the least frequent, one-time tasks last.
+    fn sort_key(&self) -> (bool, u32, bool) {
+        match self {
+            Interval::Days(days) => (*days == 0, *days, false),
+            Interval::Weeks(weeks) => (false, weeks * 7, false),
+            Interval::Months(months) => (false, months * 30, false),
+            Interval::Weekday(weekday) => (false, 7, weekday.num_days_from_monday() > 0),
+            Interval::MonthEnd => (false, 30, false),
+            Interval::MonthEndBusiness => (false, 30, true),
+        }
+    }
+
+    fn is_once(&self) -> bool {
+        *self == Interval::Days(0)
+    }
+
+    /// Computes the next due date after `start`, the day the recurrence is counted from.
+    fn next_due(&self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Interval::Days(days) => start.checked_add_signed(Duration::days((*days).into())),
+            Interval::Weeks(weeks) => start.checked_add_signed(Duration::weeks((*weeks).into())),
+            // chrono clamps to the end of shorter months, so Jan 31 + 1mo is the last day of February
+            Interval::Months(months) => start.checked_add_months(Months::new(*months)),
+            Interval::Weekday(weekday) => {
+                let days_ahead = (7 + weekday.num_days_from_monday() - start.weekday().num_days_from_monday()) % 7;
+                let days_ahead = if days_ahead == 0 { 7 } else { days_ahead };
+                start.checked_add_signed(Duration::days(days_ahead.into()))
+            },
+            Interval::MonthEnd | Interval::MonthEndBusiness => {
+                // the month end of the month of start is only next if it has not passed yet
+                let this_month = self.month_end(start, 0)?;
+                if this_month > start {
+                    Some(this_month)
+                } else {
+                    self.month_end(start, 1)
+                }
+            },
+        }
+    }
+
+    /// The last day, or last weekday for `MonthEndBusiness`, of the month `months` after the
+    /// month of `date`.
+    fn month_end(&self, date: NaiveDate, months: u32) -> Option<NaiveDate> {
+        // the last day of a month is the day before the first of the month after
+        let month_end = date.with_day(1)?.checked_add_months(Months::new(months + 1))?.pred_opt()?;
+        if *self == Interval::MonthEnd {
+            return Some(month_end);
+        }
+        match month_end.weekday() {
+            Weekday::Sat => month_end.checked_sub_signed(Duration::days(1)),
+            Weekday::Sun => month_end.checked_sub_signed(Duration::days(2)),
+            _ => Some(month_end),
+        }
+    }
+}
+
+/// Longest task name tables show before cutting it off.
+const MAX_DISPLAY_NAME: usize = 60;
+
+/// A task of the checklist, one line of the checklist file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskEntry {
     task_name: String,
     due_date: NaiveDate,
-    interval: u32,
+    interval: Interval,
+    anchor: Anchor,
+    /// Day until which `list` leaves the task out.
+    hidden_until: Option<NaiveDate>,
+    url: Option<String>,
+    /// Stable id assigned by `add`, so `#id` keeps referring to the task when it moves.
+    id: Option<u32>,
+    /// Amount `check` has to reach before the task counts as done, e.g. pages to read.
+    target: Option<u32>,
+    progress: u32,
+    /// Due date before the last `check` rescheduled the task, for `uncheck`.
+    previous_due: Option<NaiveDate>,
+    priority: Priority,
+    tags: Vec<String>,
 }
 
 impl TaskEntry {
+    /// A task with every optional field at its default, as `add` without flags creates it.
+    /// Fails for names the checklist file cannot hold and intervals `add` would reject.
+    /// Surrounding whitespace is trimmed from the name.
+    pub fn new(task_name: String, due_date: NaiveDate, interval: Interval) -> Result<TaskEntry, ChecklistError> {
+        let task_name = clean_task_name(&task_name)?;
+        Interval::parse(&interval.serialize())?;
+
+        Ok(TaskEntry {
+            task_name,
+            due_date,
+            interval,
+            anchor: Anchor::Last,
+            hidden_until: None,
+            url: None,
+            id: None,
+            target: None,
+            progress: 0,
+            previous_due: None,
+            priority: Priority::Medium,
+            tags: vec![],
+        })
+    }
+
+    pub fn with_anchor(self, anchor: Anchor) -> TaskEntry {
+        TaskEntry { anchor, ..self }
+    }
+
+    pub fn with_url(self, url: String) -> TaskEntry {
+        TaskEntry { url: Some(url), ..self }
+    }
+
+    /// Fails for a target of zero, which could never be reached.
+    pub fn with_target(self, target: u32) -> Result<TaskEntry, ChecklistError> {
+        if target == 0 {
+            return Err(ChecklistError::Other("target must be a positive whole number".to_string()));
+        }
+        Ok(TaskEntry { target: Some(target), ..self })
+    }
+
+    pub fn with_priority(self, priority: Priority) -> TaskEntry {
+        TaskEntry { priority, ..self }
+    }
+
+    /// Fails for tags the tags column cannot hold.
+    pub fn with_tag(mut self, tag: String) -> Result<TaskEntry, ChecklistError> {
+        check_tag(&tag)?;
+        self.tags.push(tag);
+        Ok(self)
+    }
+
+    pub fn task_name(&self) -> &str {
+        &self.task_name
+    }
+
+    pub fn due_date(&self) -> NaiveDate {
+        self.due_date
+    }
+
+    pub fn interval(&self) -> Interval {
+        self.interval
+    }
+
+    pub fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub fn hidden_until(&self) -> Option<NaiveDate> {
+        self.hidden_until
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn id(&self) -> Option<u32> {
+        self.id
+    }
+
+    pub fn target(&self) -> Option<u32> {
+        self.target
+    }
+
+    pub fn progress(&self) -> u32 {
+        self.progress
+    }
+
+    pub fn previous_due(&self) -> Option<NaiveDate> {
+        self.previous_due
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
     fn serialize(&self) -> String {
-        format!("{},{},{}", &self.task_name, &self.due_date, if self.interval == 0 {&0} else {&self.interval})
+        let mut serialization = format!("{},{},{}", csv_field(&self.task_name), &self.due_date, self.interval.serialize());
+
+        // optional columns are only written when they differ from their defaults
+        let mut optional: Vec<Option<String>> = vec![
+            if self.anchor == Anchor::Last { None } else { Some(self.anchor.serialize().to_string()) },
+            self.hidden_until.map(|date| date.to_string()),
+            self.url.as_deref().map(csv_field),
+            self.id.map(|id| id.to_string()),
+            self.target.map(|target| target.to_string()),
+            if self.progress == 0 { None } else { Some(self.progress.to_string()) },
+            self.previous_due.map(|date| date.to_string()),
+            if self.priority == Priority::Medium { None } else { Some(self.priority.serialize().to_string()) },
+            if self.tags.is_empty() { None } else { Some(csv_field(&self.tags.join(";"))) },
+        ];
+        while let Some(None) = optional.last() {
+            optional.pop();
+        }
+        for field in optional {
+            serialization.push(',');
+            serialization.push_str(&field.unwrap_or_default());
+        }
+
+        serialization
     }
 
-    fn deserialize(serialization: &str) -> Result<TaskEntry, String> {
-        let v: Vec<&str> = serialization.split(',').collect();
-        if v.len() != 3 {
-            return Err("incorrect number of arguments for deserialization, expected 3".to_string());
+    fn deserialize(serialization: &str) -> Result<TaskEntry, ChecklistError> {
+        let fields = split_csv_line(serialization)?;
+        let v: Vec<&str> = fields.iter().map(String::as_str).collect();
+        if v.len() < 3 || v.len() > 12 {
+            return Err(ChecklistError::Parse("incorrect number of arguments for deserialization, expected 3 to 12".to_string()));
         }
 
         let due_date = match NaiveDate::parse_from_str(&v[1], "%Y-%m-%d") {
             Ok(date) => date,
-            Err(e) => return Err(e.to_string())
+            Err(e) => return Err(ChecklistError::Parse(e.to_string()))
         };
 
-        let interval = match v[2].parse::<u32>() {
-            Ok(content) => content,
-            Err(e) => return Err(e.to_string())
+        let interval = Interval::parse(v[2])?;
+
+        let anchor = match v.get(3) {
+            Some(anchor) if !anchor.is_empty() => Anchor::parse(anchor)?,
+            _ => Anchor::Last,
+        };
+
+        let date = |index: usize| match v.get(index) {
+            Some(date) if !date.is_empty() => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(date) => Ok(Some(date)),
+                Err(e) => Err(ChecklistError::Parse(e.to_string()))
+            },
+            _ => Ok(None),
+        };
+        let hidden_until = date(4)?;
+
+        let url = match v.get(5) {
+            Some(url) if !url.is_empty() => Some(url.to_string()),
+            _ => None,
+        };
+
+        let number = |index: usize| match v.get(index) {
+            Some(number) if !number.is_empty() => match number.parse::<u32>() {
+                Ok(number) => Ok(Some(number)),
+                Err(e) => Err(ChecklistError::Parse(e.to_string()))
+            },
+            _ => Ok(None),
+        };
+        let id = number(6)?;
+        let target = number(7)?;
+        let progress = number(8)?.unwrap_or(0);
+        let previous_due = date(9)?;
+        let priority = match v.get(10) {
+            Some(priority) if !priority.is_empty() => Priority::parse(priority)?,
+            _ => Priority::Medium,
+        };
+        let tags = match v.get(11) {
+            Some(tags) if !tags.is_empty() => tags.split(';').map(|tag| tag.to_string()).collect(),
+            _ => vec![],
         };
 
         Ok(TaskEntry {
             task_name: v[0].to_string(),
             due_date,
-            interval
+            interval,
+            anchor,
+            hidden_until,
+            url,
+            id,
+            target,
+            progress,
+            previous_due,
+            priority,
+            tags,
         })
     }
     
-    #[warn(dead_code)]
-    fn build(task_name: String, due_date: String, interval: u32) 
-        -> Result<TaskEntry, String> {
-        if task_name.contains(',') {
-            return Err("task name must not contain commas".to_string());
-        }
+    /// Parses `[task_name] [due_date] [interval](optional, once)` as given on the command line.
+    fn from_args(args: &[String]) -> Result<TaskEntry, ChecklistError> {
+        let interval = if args.len() < 3 {
+            "0"
+        } else {
+            &args[2]
+        };
 
-        let due_date = match NaiveDate::parse_from_str(&due_date, "%Y-%m-%d") {
-            Ok(date) => date,
-            Err(e) => return Err(e.to_string())
+        let task_name = clean_task_name(&args[0])?;
+        TaskEntry::deserialize(format!("{},{},{}", csv_field(&task_name), &args[1], interval).as_str())
+    }
+
+    fn json_fields(&self) -> Vec<(&'static str, String)> {
+        let optional = |value: Option<String>| match value {
+            Some(value) => json::quote(&value),
+            None => "null".to_string(),
+        };
+
+        vec![
+            ("task_name", json::quote(&self.task_name)),
+            ("due_date", json::quote(&self.due_date.to_string())),
+            ("interval", match self.interval {
+                Interval::Days(days) => days.to_string(),
+                interval => json::quote(&interval.serialize()),
+            }),
+            ("anchor", json::quote(self.anchor.serialize())),
+            ("hidden_until", optional(self.hidden_until.map(|date| date.to_string()))),
+            ("url", optional(self.url.clone())),
+            ("id", match self.id {
+                Some(id) => id.to_string(),
+                None => "null".to_string(),
+            }),
+            ("target", match self.target {
+                Some(target) => target.to_string(),
+                None => "null".to_string(),
+            }),
+            ("progress", self.progress.to_string()),
+            ("previous_due", optional(self.previous_due.map(|date| date.to_string()))),
+            ("priority", json::quote(self.priority.serialize())),
+            ("tags", format!("[{}]", self.tags.iter().map(|tag| json::quote(tag)).collect::<Vec<String>>().join(", "))),
+        ]
+    }
+
+    fn to_json(&self) -> String {
+        json::object(&self.json_fields())
+    }
+
+    fn from_json(value: &json::Value) -> Result<TaskEntry, ChecklistError> {
+        let string = |key: &str| match value.get(key) {
+            Some(json::Value::String(text)) => Ok(Some(text.clone())),
+            Some(json::Value::Null) | None => Ok(None),
+            Some(_) => Err(ChecklistError::Parse(format!("task field {} must be a string", key))),
         };
+        let date = |key: &str| match string(key)? {
+            Some(date) => match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                Ok(date) => Ok(Some(date)),
+                Err(e) => Err(ChecklistError::Parse(e.to_string()))
+            },
+            None => Ok(None),
+        };
+
+        // JSON can hold names and tags the checklist lines cannot, so they are checked like add does
+        let task_name = match string("task_name")? {
+            Some(task_name) => clean_task_name(&task_name)?,
+            None => return Err(ChecklistError::Parse("task is missing task_name".to_string())),
+        };
+        let due_date = match date("due_date")? {
+            Some(due_date) => due_date,
+            None => return Err(ChecklistError::Parse(format!("task {} is missing due_date", task_name))),
+        };
+        let interval = match value.get("interval") {
+            Some(json::Value::Number(interval)) if *interval >= 0.0 && interval.fract() == 0.0 && *interval <= u32::MAX as f64 => Interval::Days(*interval as u32),
+            Some(json::Value::String(interval)) => Interval::parse(interval)?,
+            Some(json::Value::Null) | None => Interval::Days(0),
+            Some(_) => return Err(ChecklistError::Parse(format!("interval of task {} must be a whole number of days", task_name))),
+        };
+        let anchor = match string("anchor")? {
+            Some(anchor) => Anchor::parse(&anchor)?,
+            None => Anchor::Last,
+        };
+        let number = |key: &str| match value.get(key) {
+            Some(json::Value::Number(number)) if *number >= 0.0 && number.fract() == 0.0 && *number <= u32::MAX as f64 => Ok(Some(*number as u32)),
+            Some(json::Value::Null) | None => Ok(None),
+            Some(_) => Err(ChecklistError::Parse(format!("{} of task {} must be a whole number", key, task_name))),
+        };
+        let id = number("id")?;
+        let target = number("target")?;
+        let progress = number("progress")?.unwrap_or(0);
+        let priority = match string("priority")? {
+            Some(priority) => Priority::parse(&priority)?,
+            None => Priority::Medium,
+        };
+        let mut tags = vec![];
+        match value.get("tags") {
+            Some(json::Value::Array(values)) => for tag in values {
+                match tag {
+                    json::Value::String(tag) => {
+                        check_tag(tag)?;
+                        tags.push(tag.clone());
+                    },
+                    _ => return Err(ChecklistError::Parse(format!("tags of task {} must be strings", task_name))),
+                }
+            },
+            Some(json::Value::Null) | None => {},
+            Some(_) => return Err(ChecklistError::Parse(format!("tags of task {} must be an array", task_name))),
+        }
 
         Ok(TaskEntry {
             task_name,
             due_date,
             interval,
+            anchor,
+            hidden_until: date("hidden_until")?,
+            url: string("url")?,
+            id,
+            target,
+            progress,
+            previous_due: date("previous_due")?,
+            priority,
+            tags,
         })
     }
 
+    /// Describes the task relative to `today`, e.g. "Dishes — due in 2 days (every 7 days)".
+    fn humanized(&self, today: NaiveDate) -> String {
+        let days = |n: i64| if n == 1 { "1 day".to_string() } else { format!("{} days", n) };
+
+        let due = match (self.due_date - today).num_days() {
+            0 => "due today".to_string(),
+            n if n > 0 => format!("due in {}", days(n)),
+            n => format!("overdue by {}", days(-n)),
+        };
+        let interval = match self.interval {
+            Interval::Days(0) => "once".to_string(),
+            Interval::Days(1) => "every day".to_string(),
+            Interval::Days(n) => format!("every {} days", n),
+            Interval::Weeks(1) => "every week".to_string(),
+            Interval::Weeks(n) => format!("every {} weeks", n),
+            Interval::Months(1) => "every month".to_string(),
+            Interval::Months(n) => format!("every {} months", n),
+            Interval::Weekday(weekday) => format!("every {}", weekday_name(weekday)),
+            Interval::MonthEnd => "every month end".to_string(),
+            Interval::MonthEndBusiness => "every last weekday of the month".to_string(),
+        };
+
+        format!("{} — {} ({})", self.task_name, due, interval)
+    }
+
+    /// How far the due date is from `today`, e.g. "in 3 days", "today" or "2 days ago".
+    fn relative_due(&self, today: NaiveDate) -> String {
+        let days = |n: i64| if n == 1 { "1 day".to_string() } else { format!("{} days", n) };
+
+        match (self.due_date - today).num_days() {
+            0 => "today".to_string(),
+            n if n > 0 => format!("in {}", days(n)),
+            n => format!("{} ago", days(-n)),
+        }
+    }
+
+    /// The entry moved to its next due date after being checked on `today`.
+    fn rescheduled(&self, today: NaiveDate) -> Result<TaskEntry, ChecklistError> {
+        let start = match self.anchor {
+            Anchor::Last => today,
+            Anchor::Due => self.due_date,
+        };
+        // month ends are fixed days, so a task checked late moves to the first one after today
+        // and a task checked early to the first one after its due date, never skipping a month
+        let start = match self.interval {
+            Interval::MonthEnd | Interval::MonthEndBusiness => cmp::max(start, self.due_date),
+            _ => start,
+        };
+        match self.interval.next_due(start) {
+            Some(due_date) => Ok(TaskEntry { due_date, progress: 0, previous_due: Some(self.due_date), ..self.clone() }),
+            None => Err(ChecklistError::Other(format!("could not reschedule \"{}\": new due date is out of range", self.task_name))),
+        }
+    }
+
+    /// The task name as shown in tables, followed by the progress if the task has a target.
+    /// Names longer than `MAX_DISPLAY_NAME` characters are cut off, the file keeps them whole.
+    fn display_name(&self) -> String {
+        let name = if self.task_name.chars().count() > MAX_DISPLAY_NAME {
+            let cut: String = self.task_name.chars().take(MAX_DISPLAY_NAME - 1).collect();
+            format!("{}…", cut)
+        } else {
+            self.task_name.clone()
+        };
+        match self.target {
+            Some(target) => format!("{} {}/{}", name, self.progress, target),
+            None => name,
+        }
+    }
+
+    /// Whether the entries differ in anything but their id.
+    fn differs_from(&self, other: &TaskEntry) -> bool {
+        *self != TaskEntry { id: self.id, ..other.clone() }
+    }
+
+    fn is_hidden(&self, today: NaiveDate) -> bool {
+        matches!(self.hidden_until, Some(date) if date > today)
+    }
+
     fn as_table_entry(&self, column_width: [usize; 3]) -> String {
         format!(
             "{:width1$} {:width2$} {:width3$}", 
-            &self.task_name, 
+            self.display_name(), 
             &self.due_date,
-            if self.interval == 0 {"once".to_string()} else {format!("{}", &self.interval)}, 
+            self.interval.display(), 
             width1=column_width[0], 
             width2=column_width[1], 
             width3=column_width[2]
@@ -97,274 +1028,2736 @@ impl TaskEntry {
 
 impl ToString for TaskEntry {
     fn to_string(&self) -> String {
-        format!(
-            "Task name: {}, Due until: {}, Interval: {} days", 
+        let mut description = format!(
+            "Task name: {}, Due until: {}, Interval: {}", 
             &self.task_name, 
             &self.due_date, 
-            &self.interval
-        )
+            match self.interval {
+                Interval::Days(days) => format!("{} days", days),
+                interval => interval.display(),
+            }
+        );
+        if let Some(url) = &self.url {
+            description.push_str(&format!(", Link: {}", url));
+        }
+        description
     }
 }
 
+#[derive(Clone, Debug)]
 struct TaskTable {
     tasks: Vec<TaskEntry>
 }
 
 impl TaskTable {
+    /// The tasks as table rows without a header, each starting on a new line.
     fn serialize(&self) -> String {
-        let mut length: [usize; 3] = [0; 3];
-        for entry in &self.tasks {
-            length[0] = cmp::max(length[0], entry.task_name.len());
-            length[1] = cmp::max(length[1], entry.due_date.to_string().len());
-            length[2] = cmp::max(length[2], entry.interval.to_string().len());
-        } 
+        let length = self.column_widths(["", "", ""]);
 
         let mut serialization = String::new();
         for task in &self.tasks {
-            serialization = format!("{}\n{}", serialization, task.as_table_entry(length));
+            serialization.push('\n');
+            serialization.push_str(&task.as_table_entry(length));
         }
 
-        serialization.to_string()
+        serialization
     }
 
-    fn deserialize(serialization: &str) -> Result<TaskTable, String> {
+    fn deserialize(serialization: &str) -> Result<TaskTable, ChecklistError> {
         let mut tasks = vec![];
-        for line in serialization.lines() {
+        for line in serialization.lines().filter(|line| !line.is_empty()) {
             tasks.push(TaskEntry::deserialize(line)?);
         }
 
         Ok(TaskTable { tasks })
     }
-}
 
-pub fn parse_command(command_str: &str) 
-    -> Result<fn(config: Config) -> Result<(), String>, &'static str> {
-        
-    match command_str {
-        "add"       => Ok(add),
-        "remove"    => Ok(remove),
-        "list"      => Ok(list),
-        "check"     => Ok(check),
+    /// Like `deserialize`, but skips lines that cannot be read, returning an error for each
+    /// prefixed with its line number.
+    fn deserialize_lenient(serialization: &str) -> (TaskTable, Vec<String>) {
+        let mut tasks = vec![];
+        let mut errors = vec![];
+        for (i, line) in serialization.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+            match TaskEntry::deserialize(line) {
+                Ok(entry) => tasks.push(entry),
+                Err(e) => errors.push(format!("line {}: {}", i + 1, e)),
+            }
+        }
+
+        (TaskTable { tasks }, errors)
+    }
+
+    fn to_json(&self) -> String {
+        let entries: Vec<String> = self.tasks.iter().map(|entry| entry.to_json()).collect();
+        format!("{}\n", json::array(&entries))
+    }
+
+    fn from_json(serialization: &str) -> Result<TaskTable, ChecklistError> {
+        if serialization.trim().is_empty() {
+            return Ok(TaskTable { tasks: vec![] });
+        }
+
+        let entries = match json::parse(serialization).map_err(ChecklistError::Parse)? {
+            json::Value::Array(entries) => entries,
+            _ => return Err(ChecklistError::Parse("JSON checklist must be an array of tasks".to_string())),
+        };
+
+        let mut tasks = vec![];
+        for entry in &entries {
+            tasks.push(TaskEntry::from_json(entry)?);
+        }
+
+        Ok(TaskTable { tasks })
+    }
+
+    /// Reads the `content` of the checklist file at `file_path`, JSON or comma separated lines.
+    fn parse(file_path: &str, content: &str) -> Result<TaskTable, ChecklistError> {
+        if !is_json(file_path) {
+            return TaskTable::deserialize(content);
+        }
+        match TaskTable::from_json(content) {
+            Ok(table) => Ok(table),
+            Err(e) => Err(ChecklistError::Parse(format!("{}: {}", file_path, e))),
+        }
+    }
+
+    fn load(file_path: &str) -> Result<TaskTable, ChecklistError> {
+        match read_checklist(file_path) {
+            Ok(content) => TaskTable::parse(file_path, &content),
+            Err(e) => Err(ChecklistError::io(file_path, e))
+        }
+    }
+
+    /// Computes the table column widths needed to fit `headers` and every task.
+    fn column_widths(&self, headers: [&str; 3]) -> [usize; 3] {
+        let mut length: [usize; 3] = [0; 3];
+        for entry in &self.tasks {
+            length[0] = cmp::max(length[0], entry.display_name().chars().count());
+            length[1] = cmp::max(length[1], entry.due_date.to_string().len());
+            length[2] = cmp::max(length[2], entry.interval.display().len());
+        }
+        for i in 0..3 {
+            length[i] = cmp::max(length[i], headers[i].len());
+        }
+        length
+    }
+
+    /// The checklist lines `save` writes.
+    fn serialize_lines(&self) -> String {
+        let lines: Vec<String> = self.tasks.iter().map(|entry| entry.serialize()).collect();
+        lines.join("\n")
+    }
+
+    fn save(&self, file_path: &str) -> Result<(), ChecklistError> {
+        let content = if is_json(file_path) { self.to_json() } else { self.serialize_lines() };
+        match write_checklist(file_path, &content) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ChecklistError::Io(e))
+        }
+    }
+
+    fn insert(&mut self, entry: TaskEntry, position: AddPosition) {
+        let index = match position {
+            AddPosition::Top => 0,
+            AddPosition::Bottom => self.tasks.len(),
+            AddPosition::Sorted => self.tasks.iter()
+                .position(|existing| existing.due_date > entry.due_date)
+                .unwrap_or(self.tasks.len()),
+        };
+        self.tasks.insert(index, entry);
+    }
+
+    /// Keeps only the earliest due task of each name, returning how many were dropped.
+    fn dedupe(&mut self) -> usize {
+        let count = self.tasks.len();
+        let mut kept: Vec<TaskEntry> = vec![];
+        for entry in self.tasks.drain(..) {
+            match kept.iter_mut().find(|existing| existing.task_name == entry.task_name) {
+                Some(existing) => {
+                    if entry.due_date < existing.due_date {
+                        *existing = entry;
+                    }
+                },
+                None => kept.push(entry),
+            }
+        }
+        self.tasks = kept;
+        count - self.tasks.len()
+    }
+
+    fn find(&self, task_name: &str) -> Option<&TaskEntry> {
+        self.tasks.iter().find(|entry| entry.task_name == task_name)
+    }
+
+    /// Like `find`, but lets the task be changed in place.
+    fn find_mut(&mut self, task_name: &str) -> Option<&mut TaskEntry> {
+        self.tasks.iter_mut().find(|entry| entry.task_name == task_name)
+    }
+
+    /// Finds the index of the task called `reference`, or with the given id if it is `#id`.
+    fn locate(&self, reference: &str) -> Result<usize, ChecklistError> {
+        self.locate_due(reference, None)
+    }
+
+    /// Like `locate`, but only among the tasks due on `due_date` if given, which tells apart
+    /// tasks sharing a name. Fails if that still leaves more than one task.
+    fn locate_due(&self, reference: &str, due_date: Option<NaiveDate>) -> Result<usize, ChecklistError> {
+        let id = reference.strip_prefix('#').and_then(|id| id.parse::<u32>().ok());
+        let mut matching = self.tasks.iter().enumerate()
+            .filter(|(_, entry)| match id {
+                Some(id) => entry.id == Some(id),
+                None => entry.task_name == reference,
+            })
+            .filter(|(_, entry)| due_date.is_none_or(|due_date| entry.due_date == due_date))
+            .map(|(index, _)| index);
+
+        let index = match matching.next() {
+            Some(index) => index,
+            None => return Err(ChecklistError::TaskNotFound(reference.to_string(), due_date)),
+        };
+        match due_date {
+            Some(due_date) if matching.next().is_some() => {
+                Err(ChecklistError::Other(format!("several tasks \"{}\" are due on {}, use #id to pick one", reference, due_date)))
+            },
+            _ => Ok(index),
+        }
+    }
+
+    /// The id for a new task, one more than the highest id in use.
+    fn next_id(&self) -> u32 {
+        self.tasks.iter().filter_map(|entry| entry.id).max().unwrap_or(0) + 1
+    }
+
+    /// Compares two tables by task name, treating `self` as the old and `other` as the new state.
+    fn diff<'a>(&'a self, other: &'a TaskTable) -> TableDiff<'a> {
+        let mut diff = TableDiff { removed: vec![], added: vec![], changed: vec![] };
+
+        for entry in &self.tasks {
+            match other.find(&entry.task_name) {
+                Some(other_entry) => {
+                    if entry.differs_from(other_entry) {
+                        diff.changed.push((entry, other_entry));
+                    }
+                },
+                None => diff.removed.push(entry),
+            }
+        }
+
+        for entry in &other.tasks {
+            if self.find(&entry.task_name).is_none() {
+                diff.added.push(entry);
+            }
+        }
+
+        diff
+    }
+}
+
+/// A checklist file opened for use from other programs. The file is locked like for a
+/// command changing it until the `Checklist` is dropped, and changes are only written by `save`.
+///
+/// ```no_run
+/// use checklist::{Checklist, Interval, TaskEntry};
+/// use chrono::NaiveDate;
+///
+/// let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+/// let mut checklist = Checklist::open("checklist.csv")?;
+/// checklist.add_task(TaskEntry::new("Water plants".to_string(), today, Interval::Weeks(1))?)?;
+/// checklist.check_task("Water plants", today)?;
+/// for entry in checklist.list_tasks() {
+///     println!("{} is due {}", entry.task_name(), entry.due_date());
+/// }
+/// checklist.save()?;
+/// # Ok::<(), checklist::ChecklistError>(())
+/// ```
+#[derive(Debug)]
+pub struct Checklist {
+    file_path: Option<String>,
+    table: TaskTable,
+    _lock: Option<Lock>,
+}
+
+impl Checklist {
+    pub fn open(file_path: &str) -> Result<Checklist, ChecklistError> {
+        let lock = Lock::acquire(file_path)?;
+        Ok(Checklist {
+            file_path: Some(file_path.to_string()),
+            table: TaskTable::load(file_path)?,
+            _lock: Some(lock),
+        })
+    }
+
+    /// A checklist read from `content` in the comma separated checklist file format that is
+    /// not tied to any file, so `content` returns the result instead of `save` writing it.
+    ///
+    /// ```
+    /// use checklist::Checklist;
+    ///
+    /// let mut checklist = Checklist::from_content("Dishes,2024-06-01,1\nTaxes,2024-07-01,0")?;
+    /// checklist.remove_task("Taxes")?;
+    /// assert_eq!(checklist.content(), "Dishes,2024-06-01,1");
+    /// # Ok::<(), checklist::ChecklistError>(())
+    /// ```
+    pub fn from_content(content: &str) -> Result<Checklist, ChecklistError> {
+        Ok(Checklist {
+            file_path: None,
+            table: TaskTable::deserialize(content)?,
+            _lock: None,
+        })
+    }
+
+    /// The checklist in the comma separated checklist file format.
+    pub fn content(&self) -> String {
+        self.table.serialize_lines()
+    }
+
+    /// The tasks in file order.
+    pub fn list_tasks(&self) -> Vec<TaskEntry> {
+        self.table.tasks.clone()
+    }
+
+    /// Adds `entry` at the top with a new id, which is returned. Fails if a task with the same
+    /// name exists.
+    pub fn add_task(&mut self, entry: TaskEntry) -> Result<u32, ChecklistError> {
+        self.add_task_at(entry, AddPosition::Top)
+    }
+
+    fn add_task_at(&mut self, entry: TaskEntry, position: AddPosition) -> Result<u32, ChecklistError> {
+        if self.table.find(&entry.task_name).is_some() {
+            return Err(ChecklistError::DuplicateTask(entry.task_name));
+        }
+
+        let id = self.table.next_id();
+        self.table.insert(TaskEntry { id: Some(id), ..entry }, position);
+        Ok(id)
+    }
+
+    /// Removes and returns the task called `reference`, or with the given id if it is `#id`.
+    pub fn remove_task(&mut self, reference: &str) -> Result<TaskEntry, ChecklistError> {
+        self.remove_task_due(reference, None)
+    }
+
+    fn remove_task_due(&mut self, reference: &str, due_date: Option<NaiveDate>) -> Result<TaskEntry, ChecklistError> {
+        let index = self.table.locate_due(reference, due_date)?;
+        Ok(self.table.tasks.remove(index))
+    }
+
+    /// Checks the task called `reference` on `today`, ignoring its target. Recurring tasks are
+    /// moved to the top with their next due date, which is returned, and one-time tasks removed.
+    pub fn check_task(&mut self, reference: &str, today: NaiveDate) -> Result<Option<TaskEntry>, ChecklistError> {
+        let index = self.table.locate(reference)?;
+        self.check_at(index, today, true, AddPosition::Top)
+    }
+
+    fn check_at(&mut self, index: usize, today: NaiveDate, recurse: bool, position: AddPosition)
+        -> Result<Option<TaskEntry>, ChecklistError> {
+        let entry = self.table.tasks.remove(index);
+        if entry.interval.is_once() || !recurse {
+            return Ok(None);
+        }
+
+        // the entry was removed from the table above, so re-inserting it cannot clash with itself
+        let rescheduled = entry.rescheduled(today)?;
+        self.table.insert(rescheduled.clone(), position);
+        Ok(Some(rescheduled))
+    }
+
+    /// Writes the checklist back to the file it was opened from.
+    pub fn save(&self) -> Result<(), ChecklistError> {
+        match &self.file_path {
+            Some(file_path) => self.table.save(file_path),
+            None => Err(ChecklistError::Other("checklist was not opened from a file, use content instead".to_string())),
+        }
+    }
+}
+
+/// How urgent a task is regardless of its due date.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    fn parse(priority: &str) -> Result<Priority, ChecklistError> {
+        match priority {
+            "low"       => Ok(Priority::Low),
+            "medium"    => Ok(Priority::Medium),
+            "high"      => Ok(Priority::High),
+            _           => Err(ChecklistError::Parse(format!("invalid priority \"{}\", expected low, medium or high", priority))),
+        }
+    }
+
+    fn serialize(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+}
+
+/// How `merge` resolves tasks that exist in both files.
+#[derive(Clone, Copy)]
+enum MergeStrategy {
+    Newer,
+    Ours,
+    Theirs,
+}
+
+impl MergeStrategy {
+    fn parse(strategy: &str) -> Result<MergeStrategy, ChecklistError> {
+        match strategy {
+            "newer"     => Ok(MergeStrategy::Newer),
+            "ours"      => Ok(MergeStrategy::Ours),
+            "theirs"    => Ok(MergeStrategy::Theirs),
+            _           => Err(ChecklistError::Parse(format!("invalid merge strategy \"{}\", expected newer, ours or theirs", strategy))),
+        }
+    }
+}
+
+struct TableDiff<'a> {
+    removed: Vec<&'a TaskEntry>,
+    added: Vec<&'a TaskEntry>,
+    changed: Vec<(&'a TaskEntry, &'a TaskEntry)>,
+}
+
+impl TableDiff<'_> {
+    fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty() && self.changed.is_empty()
+    }
+
+    /// The differences as colored lines, removed tasks first, then added and changed ones.
+    fn lines(&self) -> Vec<ColoredString> {
+        let mut lines = vec![];
+        for entry in &self.removed {
+            lines.push(format!("- {}", entry.serialize()).red());
+        }
+        for entry in &self.added {
+            lines.push(format!("+ {}", entry.serialize()).green());
+        }
+        for (old, new) in &self.changed {
+            lines.push(format!("~ {} -> {}", old.serialize(), new.serialize()).yellow());
+        }
+        lines
+    }
+
+    fn print(&self) {
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Whether `reference` is `#id` rather than a task name.
+fn is_id_reference(reference: &str) -> bool {
+    reference.strip_prefix('#').is_some_and(|id| id.parse::<u32>().is_ok())
+}
+
+/// Whether the checklist at `file_path` is stored as JSON rather than comma separated lines.
+fn is_json(file_path: &str) -> bool {
+    file_path.trim_end_matches(".gz").ends_with(".json")
+}
+
+/// Reads the checklist at `file_path`, decompressing it if the path ends in `.gz`.
+fn read_checklist(file_path: &str) -> Result<String, Error> {
+    if file_path == STDIO_PATH {
+        return STDIN_CHECKLIST.clone().map_err(Error::other);
+    }
+
+    let content = if !file_path.ends_with(".gz") {
+        fs::read_to_string(file_path)?
+    } else {
+        let mut content = String::new();
+        GzDecoder::new(fs::File::open(file_path)?).read_to_string(&mut content)?;
+        content
+    };
+
+    debug!("read {} lines from {}", content.lines().count(), file_path);
+    Ok(content)
+}
+
+/// Writes the checklist to `file_path`, compressing it if the path ends in `.gz`.
+fn write_checklist(file_path: &str, content: &str) -> Result<(), Error> {
+    debug!("writing {}:\n{}", file_path, content);
+
+    if file_path == STDIO_PATH {
+        STDOUT_WRITTEN.store(true, Ordering::Relaxed);
+        let mut stdout = io::stdout();
+        return writeln!(stdout, "{}", content).and_then(|_| stdout.flush());
+    }
+
+    // the content goes to a file next to the checklist that is then renamed over it,
+    // so a crash leaves either the old or the new checklist behind
+    let temp_path = format!("{}.{}.tmp", file_path, process::id());
+    let write = || -> Result<(), Error> {
+        let mut file = fs::File::create(&temp_path)?;
+        if file_path.ends_with(".gz") {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(content.as_bytes())?;
+            file = encoder.finish()?;
+        } else {
+            file.write_all(content.as_bytes())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, file_path)
+    };
+
+    let result = write();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Output layout for `list --template-file`, split into `[header]`, `[row]` and `[footer]` sections.
+struct Template {
+    header: String,
+    row: String,
+    footer: String,
+}
+
+impl Template {
+    const ROW_FIELDS: [&'static str; 6] = ["task_name", "due_date", "interval", "anchor", "overdue", "url"];
+    const OUTER_FIELDS: [&'static str; 1] = ["count"];
+
+    fn load(file_path: &str) -> Result<Template, ChecklistError> {
+        let content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => return Err(ChecklistError::io(file_path, e))
+        };
+
+        let mut template = Template { header: String::new(), row: String::new(), footer: String::new() };
+        let mut section = None;
+        for line in content.lines() {
+            match line.trim() {
+                "[header]" => section = Some(&mut template.header),
+                "[row]" => section = Some(&mut template.row),
+                "[footer]" => section = Some(&mut template.footer),
+                _ => match &mut section {
+                    Some(text) => {
+                        text.push_str(line);
+                        text.push('\n');
+                    },
+                    None => return Err(ChecklistError::Other(format!("{}: text outside of a [header], [row] or [footer] section", file_path))),
+                },
+            }
+        }
+
+        let allowed = |fields: &[&str], name: &str| if fields.contains(&name) { Some(String::new()) } else { None };
+        Template::fill(&template.header, |name| allowed(&Template::OUTER_FIELDS, name))?;
+        Template::fill(&template.row, |name| allowed(&Template::ROW_FIELDS, name))?;
+        Template::fill(&template.footer, |name| allowed(&Template::OUTER_FIELDS, name))?;
+
+        Ok(template)
+    }
+
+    /// Replaces every `{field}` in `text` using `lookup`, with `{{` and `}}` standing for literal braces.
+    fn fill(text: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, ChecklistError> {
+        let mut filled = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    filled.push('{');
+                },
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    filled.push('}');
+                },
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    match lookup(&name) {
+                        Some(value) => filled.push_str(&value),
+                        None => return Err(ChecklistError::Parse(format!("unknown template field {{{}}}", name))),
+                    }
+                },
+                _ => filled.push(c),
+            }
+        }
+
+        Ok(filled)
+    }
+
+    fn render(&self, tasks: &[&TaskEntry], today: NaiveDate) -> Result<String, ChecklistError> {
+        let count = |name: &str| if name == "count" { Some(tasks.len().to_string()) } else { None };
+
+        let mut rendered = Template::fill(&self.header, count)?;
+        for entry in tasks {
+            rendered.push_str(&Template::fill(&self.row, |name| match name {
+                "task_name" => Some(entry.task_name.clone()),
+                "due_date" => Some(entry.due_date.to_string()),
+                "interval" => Some(entry.interval.display()),
+                "anchor" => Some(entry.anchor.serialize().to_string()),
+                "overdue" => Some((entry.due_date < today).to_string()),
+                "url" => Some(entry.url.clone().unwrap_or_default()),
+                _ => None,
+            })?);
+        }
+        rendered.push_str(&Template::fill(&self.footer, count)?);
+
+        Ok(rendered)
+    }
+}
+
+/// Small xorshift generator, seedable so picks can be reproduced.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // xorshift gets stuck on a zero state
+        Rng { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+
+    fn from_time() -> Rng {
+        let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_nanos() as u64,
+            Err(_) => 0,
+        };
+        Rng::new(nanos)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns a number in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Parses `YYYY-MM-DD`, `today`, `tomorrow`, `yesterday`, `+N` for N days from `today` or
+/// a weekday name, which means the next such day counting from `today`.
+fn parse_date(input: &str, today: NaiveDate) -> Result<NaiveDate, ChecklistError> {
+    match input {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Duration::days(1)),
+        "yesterday" => return Ok(today - Duration::days(1)),
+        _ => {},
+    }
+
+    if let Some(days) = input.strip_prefix('+') {
+        if let Some(date) = days.parse::<u32>().ok().and_then(|days| today.checked_add_signed(Duration::days(days.into()))) {
+            return Ok(date);
+        }
+    }
+
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        let days_ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        return Ok(today + Duration::days(days_ahead.into()));
+    }
+
+    match NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        Ok(date) => Ok(date),
+        Err(_) => Err(ChecklistError::Parse(format!("invalid date \"{}\", expected YYYY-MM-DD, today, tomorrow, yesterday, +N or a weekday", input))),
+    }
+}
+
+/// The completion log kept next to the checklist file.
+fn history_path(file_path: &str) -> String {
+    format!("{}.history", file_path)
+}
+
+/// Appends the checked tasks to the completion log, creating it on first use.
+fn append_history(file_path: &str, task_names: &[&String], date: NaiveDate) -> Result<(), ChecklistError> {
+    if task_names.is_empty() {
+        return Ok(());
+    }
+
+    let mut lines = String::new();
+    for task_name in task_names {
+        lines.push_str(&format!("{},{}\n", csv_field(task_name), date));
+    }
+
+    let path = history_path(file_path);
+    debug!("appending {} tasks to {}", task_names.len(), path);
+    let result = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()));
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(ChecklistError::io(&path, e)),
+    }
+}
+
+/// Trims `task_name`, failing if nothing is left or it has line breaks. Commas are quoted in the
+/// file, but every task has to stay on its own line.
+fn clean_task_name(task_name: &str) -> Result<String, ChecklistError> {
+    let task_name = task_name.trim();
+    if task_name.is_empty() {
+        return Err(ChecklistError::Parse("task name must not be empty or only whitespace".to_string()));
+    }
+    if task_name.contains(['\n', '\r']) {
+        return Err(ChecklistError::Parse("task name must not contain line breaks".to_string()));
+    }
+    if is_id_reference(task_name) {
+        return Err(ChecklistError::Parse(format!("task name must not look like an id, {} refers to the task with that id", task_name)));
+    }
+    Ok(task_name.to_string())
+}
+
+/// Fails for tags the tags column cannot hold, which are separated by semicolons.
+fn check_tag(tag: &str) -> Result<(), ChecklistError> {
+    if tag.is_empty() || tag.contains([';', '\n', '\r']) {
+        return Err(ChecklistError::Parse(format!("invalid tag \"{}\", tags must not be empty or contain semicolons", tag)));
+    }
+    Ok(())
+}
+
+/// Quotes `field` for a checklist line if it contains a comma or double quote,
+/// doubling the quotes inside.
+fn csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits a checklist line into its fields, undoing the quoting of `csv_field`.
+fn split_csv_line(line: &str) -> Result<Vec<String>, ChecklistError> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    },
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(ChecklistError::Parse("unterminated quoted field".to_string())),
+                }
+            }
+            if !matches!(chars.peek(), Some(',') | None) {
+                return Err(ChecklistError::Parse("unexpected text after quoted field".to_string()));
+            }
+        } else {
+            while let Some(c) = chars.peek() {
+                if *c == ',' {
+                    break;
+                }
+                field.push(*c);
+                chars.next();
+            }
+        }
+        fields.push(field);
+
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+/// Fails with the errors of the tasks a command could not handle, keeping a single error as it is.
+fn combine_errors(mut errors: Vec<ChecklistError>) -> Result<(), ChecklistError> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            Err(ChecklistError::Other(messages.join("\n")))
+        },
+    }
+}
+
+/// Removes `flag` from `args`, returning whether it was present.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != len
+}
+
+/// Removes `flag` and the value following it from `args`, returning the value.
+fn take_option(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, ChecklistError> {
+    let index = match args.iter().position(|arg| arg == flag) {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+
+    if index + 1 >= args.len() {
+        return Err(ChecklistError::Other(format!("missing value for {}", flag)));
+    }
+
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+/// Global options `Config::build` takes a value for.
+const GLOBAL_OPTIONS: [&str; 4] = ["--file", "--assume-today", "--list", "--color"];
+
+/// Global flags `Config::build` takes without a value.
+const GLOBAL_FLAGS: [&str; 4] = ["--no-recurse", "--quiet", "--dry-run", "--dedupe-on-load"];
+
+/// The index of the command in `args`, the first argument after the program name that is
+/// not a global option or its value, since global options may come before the command.
+pub fn command_position(args: &[String]) -> Option<usize> {
+    let mut index = 1;
+    while index < args.len() {
+        let arg = args[index].as_str();
+        if GLOBAL_OPTIONS.contains(&arg) {
+            index += 2;
+        } else if GLOBAL_FLAGS.contains(&arg) || arg.starts_with("--color=") {
+            index += 1;
+        } else {
+            return Some(index);
+        }
+    }
+    None
+}
+
+pub fn parse_command(command_str: &str) 
+    -> Result<fn(config: Config) -> Result<(), ChecklistError>, &'static str> {
+        
+    debug!("command {}", command_str);
+
+    match command_str {
+        "add"       => Ok(add),
+        "remove"    => Ok(remove),
+        "list"      => Ok(list),
+        "check"     => Ok(check),
         "uncheck"   => Ok(uncheck),
+        "status"    => Ok(status),
+        "diff"      => Ok(diff),
+        "merge"     => Ok(merge),
+        "ensure"    => Ok(ensure),
+        "progress"  => Ok(progress),
+        "random"    => Ok(random),
+        "validate"  => Ok(validate),
+        "hide"      => Ok(hide),
+        "unhide"    => Ok(unhide),
+        "due"       => Ok(due),
+        "open-url"  => Ok(open_url),
+        "stats"     => Ok(stats),
+        "convert"   => Ok(convert),
+        "purge"     => Ok(purge),
+        "clear"     => Ok(clear),
+        "export"    => Ok(export),
+        "import"    => Ok(import),
+        "dedupe"    => Ok(dedupe),
+        "complete-all-due" => Ok(complete_all_due),
+        "reindex"   => Ok(reindex),
+        "history"   => Ok(history),
+        "forecast"  => Ok(forecast),
+        "triage"    => Ok(triage),
+        "edit"      => Ok(edit),
+        "rename"    => Ok(rename),
+        "postpone" | "snooze" => Ok(postpone),
+        "search"    => Ok(search),
+        "overdue"   => Ok(overdue),
+        "upcoming"  => Ok(upcoming),
+        "count"     => Ok(count),
+        // not part of the documented commands, only meant for reporting performance issues
+        "bench"     => Ok(bench),
         _           => Err("invalid command"),
     }
-}
+}
+
+fn add(mut config: Config) -> Result<(), ChecklistError> {
+    // add     [task_name] [relative_start_date] [interval](optional, once) [--position top|bottom|sorted](optional) [--anchor last|due](optional, last) [--url url](optional)
+    //         [--replace-if-newer | --replace-if-older](optional) [--target number](optional) [--priority low|medium|high](optional, medium) [--tag tag](optional, repeatable)
+    //         [--every weekday](optional)
+    
+    let replace_if_newer = take_flag(&mut config.args, "--replace-if-newer");
+    let replace_if_older = take_flag(&mut config.args, "--replace-if-older");
+    let anchor = match take_option(&mut config.args, "--anchor")? {
+        Some(anchor) => Anchor::parse(&anchor)?,
+        None => Anchor::Last,
+    };
+    let every = match take_option(&mut config.args, "--every")? {
+        Some(weekday) => match weekday.parse::<Weekday>() {
+            Ok(weekday) => Some(Interval::Weekday(weekday)),
+            Err(_) => return Err(ChecklistError::Parse(format!("invalid weekday \"{}\"", weekday))),
+        },
+        None => None,
+    };
+    let mut tags = vec![];
+    while let Some(tag) = take_option(&mut config.args, "--tag")? {
+        check_tag(&tag)?;
+        tags.push(tag);
+    }
+    let priority = match take_option(&mut config.args, "--priority")? {
+        Some(priority) => Priority::parse(&priority)?,
+        None => Priority::Medium,
+    };
+    let target = match take_option(&mut config.args, "--target")? {
+        Some(target) => match target.parse::<u32>() {
+            Ok(target) if target > 0 => Some(target),
+            _ => return Err(ChecklistError::Parse(format!("invalid target \"{}\", expected a positive whole number", target))),
+        },
+        None => None,
+    };
+    let url = take_option(&mut config.args, "--url")?;
+    let position = match take_option(&mut config.args, "--position")? {
+        Some(position) => AddPosition::parse(&position)?,
+        None => config.add_position,
+    };
+
+    if config.args.len() < 2 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    config.args[1] = parse_date(&config.args[1], config.today())?.to_string();
+    if let Some(every) = every {
+        if config.args.len() > 2 {
+            return Err(ChecklistError::Other("give either an interval or --every, not both".to_string()));
+        }
+        config.args.push(every.serialize());
+    }
+
+    let mut checklist = Checklist::open(&config.file_path)?;
+    let entry = TaskEntry { anchor, url, target, priority, tags, ..TaskEntry::from_args(&config.args)? };
+
+    if let Some(existing) = checklist.table.find_mut(&entry.task_name) {
+        if !replace_if_newer && !replace_if_older {
+            return Err(ChecklistError::DuplicateTask(config.args[0].clone()))
+        }
+
+        let replace = (replace_if_newer && entry.due_date > existing.due_date)
+            || (replace_if_older && entry.due_date < existing.due_date);
+        if !replace {
+            report!(config, "skipped {}, stored due date {} is kept", entry.task_name, existing.due_date);
+            return Ok(());
+        }
+
+        report!(config, "replaced {}, due date {} -> {}", entry.task_name, existing.due_date, entry.due_date);
+        *existing = TaskEntry { id: existing.id, ..entry };
+        return config.save(&checklist.table);
+    }
+    
+    config.check_max_tasks(checklist.table.tasks.len(), 1)?;
+    let (task_name, due_date) = (entry.task_name.clone(), entry.due_date);
+    checklist.add_task_at(entry, position)?;
+
+    config.save(&checklist.table)?;
+    report!(config, "added \"{}\" due {}", task_name, due_date);
+
+    Ok(())
+}
+
+fn remove(mut config: Config) -> Result<(), ChecklistError> {
+    // remove  [task_name]... [--due date](optional)
+    // remove  --on [date] [--yes](optional)
+    if let Some(date) = take_option(&mut config.args, "--on")? {
+        let yes = take_flag(&mut config.args, "--yes");
+        let date = parse_date(&date, config.today())?;
+
+        let _lock = config.lock()?;
+        let mut table = TaskTable::load(&config.file_path)?;
+        let count = table.tasks.len();
+        table.tasks.retain(|entry| entry.due_date != date);
+        let removed = count - table.tasks.len();
+
+        if removed == 0 {
+            return Err(ChecklistError::Other(format!("no tasks are due on {}", date)));
+        }
+        if removed > 1 && !yes {
+            return Err(ChecklistError::Other(format!("{} tasks are due on {}, pass --yes to remove them all", removed, date)));
+        }
+
+        config.save(&table)?;
+        report!(config, "removed {} tasks due on {}", removed, date);
+        return Ok(());
+    }
+
+    let due_date = match take_option(&mut config.args, "--due")? {
+        Some(date) => Some(parse_date(&date, config.today())?),
+        None => None,
+    };
+
+    if config.args.len() < 1 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let mut checklist = Checklist::open(&config.file_path)?;
+    let mut removed = vec![];
+    let mut errors = vec![];
+    for reference in &config.args {
+        match checklist.remove_task_due(reference, due_date) {
+            Ok(entry) => removed.push(entry),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    // the tasks that were found are removed even if others are missing
+    if !removed.is_empty() {
+        config.save(&checklist.table)?;
+    }
+    for entry in &removed {
+        report!(config, "removed {}", entry.humanized(config.today()));
+    }
+
+    combine_errors(errors)
+}
+
+fn print_table_header(headers: &[&str], length: &[usize], indent: usize) {
+    let columns: Vec<String> = headers.iter()
+        .zip(length)
+        .map(|(header, width)| format!("{:width$}", header, width=width))
+        .collect();
+    println!("{}{}", " ".repeat(indent), columns.join(" "));
+    println!("{}", "-".repeat(indent + length.iter().sum::<usize>() + length.len() - 1));
+}
+
+/// Prints a table row per task, the tasks overdue on `now` in bold red.
+fn print_table_rows(tasks: &[TaskEntry], length: [usize; 3], now: NaiveDate) {
+    for entry in tasks {
+        if entry.due_date < now {
+            println!("{}", entry.as_table_entry(length).red().bold());
+        }
+        else {
+            println!("{}", entry.as_table_entry(length));
+        }
+    }
+}
+
+/// Parses a number of days, at most `MAX_INTERVAL_DAYS` so dates stay in range.
+/// `limit` starts the message for larger numbers, e.g. "cannot look ahead more than".
+fn parse_days(days: &str, limit: &str) -> Result<u32, ChecklistError> {
+    match days.parse::<u32>() {
+        Ok(days) if days <= MAX_INTERVAL_DAYS => Ok(days),
+        Ok(_) => Err(ChecklistError::Parse(format!("{} {} days", limit, MAX_INTERVAL_DAYS))),
+        Err(e) => Err(ChecklistError::Parse(format!("invalid number of days: {}", e))),
+    }
+}
+
+fn list(mut config: Config) -> Result<(), ChecklistError> {
+    // list    [--group-by week](optional) [--week-start weekday](optional, monday) [--pretty-interval-header](optional) [--no-legend](optional) [--template-file path](optional) [--show-path](optional) [--focus-worst](optional) [--no-page](optional) [--markers](optional) [--json](optional) [--json-computed](optional) [--format json|yaml](optional) [--sort name|due|interval](optional, due) [--reverse](optional) [--filter-tag tag](optional)
+    let pretty_interval_header = take_flag(&mut config.args, "--pretty-interval-header");
+    let legend = pretty_interval_header && !take_flag(&mut config.args, "--no-legend");
+    let interval_header = if pretty_interval_header { "interval (days)" } else { "interval" };
+    let group_by = take_option(&mut config.args, "--group-by")?;
+    let week_start = match take_option(&mut config.args, "--week-start")? {
+        Some(weekday) => match weekday.parse::<Weekday>() {
+            Ok(weekday) => weekday,
+            Err(_) => return Err(ChecklistError::Parse(format!("invalid week start \"{}\"", weekday))),
+        },
+        None => Weekday::Mon,
+    };
+    let markers = if take_flag(&mut config.args, "--markers") {
+        Some(config.markers.clone().unwrap_or(("!".to_string(), "*".to_string())))
+    } else {
+        config.markers.clone()
+    };
+    let json_computed = take_flag(&mut config.args, "--json-computed");
+    let json = take_flag(&mut config.args, "--json") || json_computed;
+    let format = match take_option(&mut config.args, "--format")? {
+        Some(format) if format == "json" || format == "yaml" => Some(format),
+        Some(format) => return Err(ChecklistError::Parse(format!("invalid format \"{}\", expected json or yaml", format))),
+        None if json => Some("json".to_string()),
+        None => None,
+    };
+    let no_page = take_flag(&mut config.args, "--no-page");
+    let sort = match take_option(&mut config.args, "--sort")? {
+        Some(sort) if ["name", "due", "interval"].contains(&sort.as_str()) => sort,
+        Some(sort) => return Err(ChecklistError::Parse(format!("invalid sort key \"{}\", expected name, due or interval", sort))),
+        None => "due".to_string(),
+    };
+    let reverse = take_flag(&mut config.args, "--reverse");
+    let filter_tag = take_option(&mut config.args, "--filter-tag")?;
+    let focus_worst = take_flag(&mut config.args, "--focus-worst");
+    let show_path = take_flag(&mut config.args, "--show-path") || config.show_path;
+    let template = match take_option(&mut config.args, "--template-file")? {
+        Some(file_path) => Some(Template::load(&file_path)?),
+        None => None,
+    };
+    if let Some(group) = &group_by {
+        if group != "week" {
+            return Err(ChecklistError::Parse(format!("invalid grouping \"{}\", expected week", group)));
+        }
+    }
+
+    let now = config.today();
+    let mut table = config.load_for_reading()?;
+    table.tasks.retain(|entry| !entry.is_hidden(now));
+    if let Some(tag) = &filter_tag {
+        table.tasks.retain(|entry| entry.tags.contains(tag));
+    }
+    // the file keeps its order, only the listing is sorted; keys other than due break ties by due date
+    table.tasks.sort_by(|a, b| a.due_date.cmp(&b.due_date).then_with(|| a.task_name.cmp(&b.task_name)));
+    match sort.as_str() {
+        "name" => table.tasks.sort_by(|a, b| a.task_name.cmp(&b.task_name)),
+        "interval" => table.tasks.sort_by_key(|entry| entry.interval.sort_key()),
+        _ => {},
+    }
+    if reverse {
+        table.tasks.reverse();
+    }
+
+    if let Some(format) = format {
+        let entries: Vec<Vec<(&str, String)>> = table.tasks.iter().map(|entry| {
+            let mut fields = entry.json_fields();
+            if json_computed {
+                fields.push(("days_until_due", (entry.due_date - now).num_days().to_string()));
+            }
+            fields.push(("overdue", (entry.due_date < now).to_string()));
+            fields
+        }).collect();
+        if format == "yaml" {
+            println!("{}", yaml::sequence(&entries));
+        } else {
+            let entries: Vec<String> = entries.iter().map(|fields| json::object(fields)).collect();
+            println!("{}", json::array(&entries));
+        }
+        return Ok(());
+    }
+
+    if let Some(page_threshold) = config.page_threshold {
+        if !no_page && table.tasks.len() > page_threshold && io::stdout().is_terminal() {
+            eprint!("show all {} tasks? [y/N] ", table.tasks.len());
+            let mut answer = String::new();
+            if let Err(e) = io::stdin().read_line(&mut answer) {
+                return Err(ChecklistError::Io(e));
+            }
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(template) = template {
+        let tasks: Vec<&TaskEntry> = table.tasks.iter().collect();
+        print!("{}", template.render(&tasks, now)?);
+        return Ok(());
+    }
+    
+    let headers = ["task", "due until", interval_header];
+    let length = table.column_widths(headers);
+
+    // markers show overdue and due today without relying on color
+    let marker_width = match &markers {
+        Some((overdue, today)) => cmp::max(overdue.chars().count(), today.chars().count()) + 1,
+        None => 0,
+    };
+    let marker = |entry: &TaskEntry| match &markers {
+        Some((overdue, _)) if entry.due_date < now => format!("{:width$} ", overdue, width=marker_width - 1),
+        Some((_, today)) if entry.due_date == now => format!("{:width$} ", today, width=marker_width - 1),
+        _ => " ".repeat(marker_width),
+    };
+    // the tags column only appears once some task has tags
+    let tags_width = table.tasks.iter()
+        .filter(|entry| !entry.tags.is_empty())
+        .map(|entry| entry.tags.join(";").chars().count())
+        .max()
+        .map(|width| cmp::max(width, "tags".len()));
+    // the relative due date is only computed for the listing, the file keeps the date
+    let when_width = table.tasks.iter()
+        .map(|entry| entry.relative_due(now).chars().count())
+        .fold("when".len(), cmp::max);
+    let mut columns = vec![(headers[0], length[0]), (headers[1], length[1]), (headers[2], length[2]), ("when", when_width)];
+    if let Some(tags_width) = tags_width {
+        columns.push(("tags", tags_width));
+    }
+    let (column_headers, column_widths): (Vec<&str>, Vec<usize>) = columns.into_iter().unzip();
+    print_table_header(&column_headers, &column_widths, marker_width);
+    let row = |entry: &TaskEntry| {
+        let row = format!("{} {:width$}", entry.as_table_entry(length), entry.relative_due(now), width=when_width);
+        match tags_width {
+            Some(tags_width) => format!("{} {:width$}", row, entry.tags.join(";"), width=tags_width),
+            None => row,
+        }
+    };
+
+    // with --focus-worst only the most overdue task is emphasized
+    let worst = if focus_worst {
+        table.tasks.iter()
+            .filter(|entry| entry.due_date < now)
+            .min_by_key(|entry| entry.due_date)
+            .map(|entry| entry.task_name.clone())
+    } else {
+        None
+    };
+    let print_entry = |entry: &TaskEntry| {
+        if entry.due_date >= now && entry.priority == Priority::High {
+            println!("{}{}", marker(entry), row(entry).magenta());
+        }
+        else if entry.due_date >= now {
+            println!("{}{}", marker(entry), row(entry));
+        }
+        else if !focus_worst {
+            println!("{}{}", marker(entry), row(entry).red().bold());
+        }
+        else if worst.as_ref() == Some(&entry.task_name) {
+            println!("{}{}", marker(entry), row(entry).red().bold().underline());
+        }
+        else {
+            println!("{}{}", marker(entry), row(entry).yellow());
+        }
+    };
+
+    let print_footer = || {
+        if legend {
+            println!("{}", "once = one-time, N = every N days, Nw = weeks, Nmo = months, month-end = last day of the month".dimmed());
+        }
+        if show_path {
+            println!("{}", config.file_path.dimmed());
+        }
+    };
+
+    if group_by.is_none() {
+        for entry in &table.tasks {
+            print_entry(entry);
+        }
+        print_footer();
+        return Ok(());
+    }
+
+    table.tasks.sort_by_key(|entry| entry.due_date);
+
+    let days_into_week = (7 + now.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    let start_of_week = now - Duration::days(days_into_week.into());
+    let mut current_bucket = None;
+    for entry in &table.tasks {
+        let bucket = if entry.due_date < now {
+            -1
+        } else {
+            (entry.due_date - start_of_week).num_days() / 7
+        };
+
+        if current_bucket != Some(bucket) {
+            let header = match bucket {
+                -1 => "overdue".to_string(),
+                0 => "this week".to_string(),
+                1 => "next week".to_string(),
+                weeks => format!("{} weeks out", weeks),
+            };
+            println!("{}", header.bold());
+            current_bucket = Some(bucket);
+        }
+        print_entry(entry);
+    }
+    print_footer();
+
+    Ok(())
+}
+
+fn check(mut config: Config) -> Result<(), ChecklistError> {
+    // check   [task_name]... [--due date](optional)
+    // check   [task_name] [amount](optional, 1) [--set](optional) [--due date](optional)
+    let set = take_flag(&mut config.args, "--set");
+    let due_date = match take_option(&mut config.args, "--due")? {
+        Some(date) => Some(parse_date(&date, config.today())?),
+        None => None,
+    };
+    if config.args.len() < 1 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    // a number after a single task name is the amount of progress, not another task
+    let amount = match config.args.get(1) {
+        Some(amount) if config.args.len() == 2 => amount.parse::<u32>().ok(),
+        _ => None,
+    };
+    let references = if amount.is_some() { &config.args[..1] } else { &config.args[..] };
+
+    let mut checklist = Checklist::open(&config.file_path)?;
+    let mut changed = false;
+    let mut checked = vec![];
+    let mut errors = vec![];
+    for reference in references {
+        let table = &mut checklist.table;
+        let index = match table.locate_due(reference, due_date) {
+            Ok(index) => index,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            },
+        };
+
+        match table.tasks[index].target {
+            Some(target) => {
+                let entry = &mut table.tasks[index];
+                let amount = amount.unwrap_or(1);
+                entry.progress = if set { amount } else { entry.progress.saturating_add(amount) };
+                if entry.progress < target {
+                    report!(config, "{} {}/{}", entry.task_name, entry.progress, target);
+                    changed = true;
+                    continue;
+                }
+            },
+            None if amount.is_some() || set => {
+                errors.push(ChecklistError::Other(format!("task \"{}\" has no target to count progress towards", table.tasks[index].task_name)));
+                continue;
+            },
+            None => {},
+        }
+
+        let task_name = table.tasks[index].task_name.clone();
+        let next = checklist.check_at(index, config.today(), !config.no_recurse, config.add_position)?;
+        changed = true;
+        checked.push((task_name, next.map(|entry| entry.due_date)));
+    }
+
+    // every task is handled in memory, so the file is written once however many were checked
+    if changed {
+        config.save(&checklist.table)?;
+    }
+    let names: Vec<&String> = checked.iter().map(|(task_name, _)| task_name).collect();
+    config.record_history(&names, config.today())?;
+    for (task_name, next_due) in &checked {
+        match next_due {
+            Some(next_due) => report!(config, "checked \"{}\", next due {}", task_name, next_due),
+            None => report!(config, "checked \"{}\"", task_name),
+        }
+    }
+
+    combine_errors(errors)
+}
+
+fn uncheck(config: Config) -> Result<(), ChecklistError> {
+    // uncheck [task_name]
+    if config.args.len() < 1 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let index = match table.locate(&config.args[0]) {
+        Ok(index) => index,
+        Err(e) => return Err(ChecklistError::Other(format!("{}, one-time tasks are removed by check and cannot be restored", e))),
+    };
+    let entry = &mut table.tasks[index];
+
+    // only check records where the task was before, counting back the interval would move
+    // tasks that were never checked and misses the original due date of anchor=last tasks
+    let previous_due = match entry.previous_due {
+        Some(previous_due) => previous_due,
+        None => return Err(ChecklistError::Other(format!("task \"{}\" has not been checked", entry.task_name))),
+    };
+
+    report!(config, "{} is due {} again", entry.task_name, previous_due);
+    entry.due_date = previous_due;
+    entry.previous_due = None;
+
+    config.save(&table)
+}
+
+fn status(mut config: Config) -> Result<(), ChecklistError> {
+    // status  [--code]
+    let table = config.load_for_reading()?;
+    let now = config.today();
+    let visible: Vec<&TaskEntry> = table.tasks.iter().filter(|entry| !entry.is_hidden(now)).collect();
+    let overdue = visible.iter().filter(|entry| entry.due_date < now).count();
+
+    if take_flag(&mut config.args, "--code") {
+        // exit codes above 125 are reserved by shells, so the count is capped
+        return match cmp::min(overdue, 125) {
+            0 => Ok(()),
+            code => Err(ChecklistError::Exit(code as u8)),
+        };
+    }
+
+    println!("{} overdue, {} total", overdue, visible.len());
+
+    Ok(())
+}
+
+fn diff(config: Config) -> Result<(), ChecklistError> {
+    // diff    [file_a] [file_b](optional, defaults to comparing against the checklist)
+    let (file_a, file_b) = match config.args.len() {
+        0 => return Err(ChecklistError::NotEnoughArgs),
+        1 => (config.file_path.as_str(), config.args[0].as_str()),
+        _ => (config.args[0].as_str(), config.args[1].as_str()),
+    };
+
+    let table_a = TaskTable::load(file_a)?;
+    let table_b = TaskTable::load(file_b)?;
+    let diff = table_a.diff(&table_b);
+
+    if diff.is_empty() {
+        println!("no differences");
+    }
+    else {
+        diff.print();
+    }
+
+    Ok(())
+}
+
+fn merge(mut config: Config) -> Result<(), ChecklistError> {
+    // merge   [file] [--prefer newer|ours|theirs](optional, ours) [--dry-run](optional)
+    let strategy = match take_option(&mut config.args, "--prefer")? {
+        Some(strategy) => MergeStrategy::parse(&strategy)?,
+        None => MergeStrategy::Ours,
+    };
+
+    if config.args.is_empty() {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let _lock = config.lock()?;
+    let ours = TaskTable::load(&config.file_path)?;
+    let theirs = TaskTable::load(&config.args[0])?;
+
+    let mut merged = ours.clone();
+    let (mut added, mut updated, mut kept) = (0, 0, 0);
+    for incoming in theirs.tasks {
+        let existing = merged.find_mut(&incoming.task_name);
+        match existing {
+            Some(entry) => {
+                if !entry.differs_from(&incoming) {
+                    continue;
+                }
+                let take_incoming = match strategy {
+                    MergeStrategy::Newer => incoming.due_date > entry.due_date,
+                    MergeStrategy::Ours => false,
+                    MergeStrategy::Theirs => true,
+                };
+                if take_incoming {
+                    *entry = TaskEntry { id: entry.id, ..incoming };
+                    updated += 1;
+                }
+                else {
+                    kept += 1;
+                }
+            },
+            None => {
+                let id = Some(merged.next_id());
+                merged.tasks.push(TaskEntry { id, ..incoming });
+                added += 1;
+            },
+        }
+    }
+
+    config.check_max_tasks(ours.tasks.len(), added)?;
+
+    config.save(&merged)?;
+
+    report!(config, "{} added, {} updated, {} conflicts kept", added, updated, kept);
+
+    Ok(())
+}
+
+fn ensure(mut config: Config) -> Result<(), ChecklistError> {
+    // ensure  [task_name] [due_date] [interval](optional, once) [--anchor last|due](optional, last)
+    let anchor = match take_option(&mut config.args, "--anchor")? {
+        Some(anchor) => Some(Anchor::parse(&anchor)?),
+        None => None,
+    };
+
+    if config.args.len() < 2 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let entry = TaskEntry::from_args(&config.args)?;
+    let entry = TaskEntry { anchor: anchor.unwrap_or(entry.anchor), ..entry };
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+
+    match table.find_mut(&entry.task_name) {
+        Some(existing) => {
+            // only the schedule is ensured, the task keeps its url, tags, priority and so on
+            let anchor = anchor.unwrap_or(existing.anchor);
+            if existing.due_date == entry.due_date && existing.interval == entry.interval && existing.anchor == anchor {
+                report!(config, "unchanged");
+                return Ok(());
+            }
+            existing.due_date = entry.due_date;
+            existing.interval = entry.interval;
+            existing.anchor = anchor;
+            config.save(&table)?;
+            report!(config, "updated");
+        },
+        None => {
+            config.check_max_tasks(table.tasks.len(), 1)?;
+            let id = Some(table.next_id());
+            table.insert(TaskEntry { id, ..entry }, config.add_position);
+            config.save(&table)?;
+            report!(config, "created");
+        },
+    }
+
+    Ok(())
+}
+
+fn progress(config: Config) -> Result<(), ChecklistError> {
+    // progress
+    let table = config.load_for_reading()?;
+    let total = table.tasks.len();
+    let now = config.today();
+    let on_schedule = table.tasks.iter().filter(|entry| entry.due_date >= now).count();
+
+    let percentage = (on_schedule * 100).checked_div(total).unwrap_or(100);
+    println!("{}/{} ({}%) on schedule, {} overdue", on_schedule, total, percentage, total - on_schedule);
+
+    Ok(())
+}
+
+fn random(mut config: Config) -> Result<(), ChecklistError> {
+    // random  [--due-only](optional) [--seed number](optional) [--check](optional)
+    let due_only = take_flag(&mut config.args, "--due-only");
+    let check_task = take_flag(&mut config.args, "--check");
+    let mut rng = match take_option(&mut config.args, "--seed")? {
+        Some(seed) => match seed.parse::<u64>() {
+            Ok(seed) => Rng::new(seed),
+            Err(e) => return Err(ChecklistError::Parse(format!("invalid seed: {}", e))),
+        },
+        None => Rng::from_time(),
+    };
+
+    let table = config.load_for_reading()?;
+    let now = config.today();
+    let candidates: Vec<&TaskEntry> = table.tasks.iter()
+        .filter(|entry| !entry.is_hidden(now) && (!due_only || entry.due_date <= now))
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(ChecklistError::Other("no tasks to pick from".to_string()));
+    }
+
+    let entry = candidates[rng.below(candidates.len())];
+    println!("{}", entry.humanized(now));
+
+    if check_task {
+        check(Config {
+            args: vec!(entry.task_name.clone()),
+            ..config
+        })?;
+    }
+
+    Ok(())
+}
+
+fn validate(mut config: Config) -> Result<(), ChecklistError> {
+    // validate [--fix](optional)
+    let fix = take_flag(&mut config.args, "--fix");
+
+    let _lock = config.lock()?;
+    // a JSON checklist has no lines to repair, it only has to load
+    if is_json(&config.file_path) {
+        TaskTable::load(&config.file_path)?;
+        return Ok(());
+    }
+    let checklist: String = 
+        match read_checklist(&config.file_path) {
+            Ok(content) => content,
+            Err(e) => return Err(ChecklistError::Io(e))
+        };
+
+    let mut lines: Vec<String> = vec![];
+    let mut repairs = 0;
+    let mut malformed = 0;
+    let segments: Vec<&str> = checklist.split('\n').collect();
+    for (i, segment) in segments.iter().enumerate() {
+        let line = segment.trim_end_matches('\r');
+        if line != *segment {
+            println!("line {}: removed carriage return", i + 1);
+            repairs += 1;
+        }
+
+        if line.trim().is_empty() {
+            // the empty segment after a trailing newline is not a blank line
+            if i + 1 != segments.len() || !segment.is_empty() {
+                println!("line {}: removed blank line", i + 1);
+                repairs += 1;
+            }
+            continue;
+        }
+
+        if TaskEntry::deserialize(line).is_ok() {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let trimmed = match split_csv_line(line) {
+            Ok(fields) => fields.iter().map(|field| csv_field(field.trim())).collect::<Vec<String>>().join(","),
+            Err(_) => line.to_string(),
+        };
+        match TaskEntry::deserialize(&trimmed) {
+            Ok(_) => {
+                println!("line {}: trimmed whitespace around fields", i + 1);
+                repairs += 1;
+                lines.push(trimmed);
+            },
+            Err(e) => {
+                println!("line {}: {}", i + 1, e);
+                malformed += 1;
+                lines.push(line.to_string());
+            },
+        }
+    }
+
+    if fix && repairs > 0 && config.dry_run {
+        println!("would fix {} problems, dry run, nothing was written", repairs);
+    }
+    else if fix && repairs > 0 {
+        let backup = format!("{}.bak", config.file_path);
+        if let Err(e) = fs::copy(&config.file_path, &backup) {
+            return Err(ChecklistError::Other(format!("could not back up checklist to {}: {}", backup, e)));
+        }
+        if let Err(e) = write_checklist(&config.file_path, &lines.join("\n")) {
+            return Err(ChecklistError::Io(e));
+        }
+        report!(config, "fixed {} problems, original saved to {}", repairs, backup);
+    }
+    else if repairs > 0 {
+        println!("{} problems can be fixed with --fix", repairs);
+    }
+
+    if malformed > 0 {
+        return Err(ChecklistError::Other(format!("{} malformed lines", malformed)));
+    }
+
+    Ok(())
+}
+
+fn hide(mut config: Config) -> Result<(), ChecklistError> {
+    // hide    [task_name] until [date]
+    // only the word after the task name is dropped, so a task may be called until
+    if config.args.len() > 2 && config.args[1] == "until" {
+        config.args.remove(1);
+    }
+    if config.args.len() < 2 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let hidden_until = parse_date(&config.args[1], config.today())?;
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let index = table.locate(&config.args[0])?;
+    table.tasks[index].hidden_until = Some(hidden_until);
+
+    config.save(&table)
+}
+
+fn unhide(config: Config) -> Result<(), ChecklistError> {
+    // unhide  [task_name]
+    if config.args.is_empty() {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let index = table.locate(&config.args[0])?;
+    table.tasks[index].hidden_until = None;
+
+    config.save(&table)
+}
+
+fn due(mut config: Config) -> Result<(), ChecklistError> {
+    // due     [--agenda](optional) [--days number](optional, 7)
+    let agenda = take_flag(&mut config.args, "--agenda");
+    let days = match take_option(&mut config.args, "--days")? {
+        Some(days) => parse_days(&days, "cannot look ahead more than")?,
+        None => 7,
+    };
+
+    let now = config.today();
+    let mut table = config.load_for_reading()?;
+    table.tasks.retain(|entry| !entry.is_hidden(now));
+    table.tasks.sort_by_key(|entry| entry.due_date);
+
+    let headers = ["task", "due until", "interval"];
+    if !agenda {
+        table.tasks.retain(|entry| entry.due_date <= now);
+        let length = table.column_widths(headers);
+        print_table_header(&headers, &length, 0);
+        print_table_rows(&table.tasks, length, now);
+        return Ok(());
+    }
+
+    let end = match now.checked_add_signed(Duration::days(days.into())) {
+        Some(end) => end,
+        None => return Err(ChecklistError::Other(format!("{} days from {} is out of range", days, now))),
+    };
+    table.tasks.retain(|entry| entry.due_date < end);
+    let length = table.column_widths(headers);
+
+    let past_due: Vec<&TaskEntry> = table.tasks.iter().filter(|entry| entry.due_date < now).collect();
+    if !past_due.is_empty() {
+        println!("{}", "Past due".bold());
+        for entry in past_due {
+            println!("{}", entry.as_table_entry(length).red().bold());
+        }
+    }
+
+    for day in now.iter_days().take_while(|day| *day < end) {
+        println!("{}", day.format("%Y-%m-%d %a").to_string().bold());
+        for entry in table.tasks.iter().filter(|entry| entry.due_date == day) {
+            println!("{}", entry.as_table_entry(length));
+        }
+    }
+
+    Ok(())
+}
+
+fn open_url(config: Config) -> Result<(), ChecklistError> {
+    // open-url [task_name]
+    if config.args.is_empty() {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let table = TaskTable::load(&config.file_path)?;
+    let url = match &table.tasks[table.locate(&config.args[0])?].url {
+        Some(url) => url.clone(),
+        None => return Err(ChecklistError::Other(format!("task \"{}\" has no url", config.args[0]))),
+    };
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    match command.arg(&url).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(ChecklistError::Other(format!("could not open {}: browser exited with {}", url, status))),
+        Err(e) => Err(ChecklistError::Other(format!("could not open {}: {}", url, e))),
+    }
+}
+
+fn stats(mut config: Config) -> Result<(), ChecklistError> {
+    // stats   [--histogram](optional) [--days number](optional, 7)
+    let histogram = take_flag(&mut config.args, "--histogram");
+    let days = match take_option(&mut config.args, "--days")? {
+        Some(days) => parse_days(&days, "cannot look ahead more than")?,
+        None => 7,
+    };
+
+    let table = config.load_for_reading()?;
+    let now = config.today();
+
+    if !histogram {
+        let recurring = table.tasks.iter().filter(|entry| !entry.interval.is_once()).count();
+        println!("total     {}", table.tasks.len());
+        println!("overdue   {}", table.tasks.iter().filter(|entry| entry.due_date < now).count());
+        println!("due today {}", table.tasks.iter().filter(|entry| entry.due_date == now).count());
+        println!("recurring {}", recurring);
+        println!("once      {}", table.tasks.len() - recurring);
+        return Ok(());
+    }
+
+    let mut buckets: Vec<(String, usize)> = vec![
+        ("overdue".to_string(), table.tasks.iter().filter(|entry| entry.due_date < now).count()),
+    ];
+    for day in now.iter_days().take(days as usize) {
+        buckets.push((day.to_string(), table.tasks.iter().filter(|entry| entry.due_date == day).count()));
+    }
+
+    // bars are scaled down once the busiest day would not fit
+    const MAX_BAR: usize = 40;
+    let largest = buckets.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let label_width = buckets.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, count) in &buckets {
+        let bar = if largest > MAX_BAR { count * MAX_BAR / largest } else { *count };
+        println!("{:width$} {} {}", label, "█".repeat(bar), count, width=label_width);
+    }
+
+    Ok(())
+}
+
+fn convert(mut config: Config) -> Result<(), ChecklistError> {
+    // convert --to json|csv
+    let extension = match take_option(&mut config.args, "--to")? {
+        Some(format) if format == "json" || format == "csv" => format,
+        Some(format) => return Err(ChecklistError::Parse(format!("invalid format \"{}\", expected json or csv", format))),
+        None => return Err(ChecklistError::NotEnoughArgs),
+    };
+
+    let compressed = config.file_path.ends_with(".gz");
+    let base = config.file_path.trim_end_matches(".gz");
+    let base = base.strip_suffix(".json").or(base.strip_suffix(".csv")).unwrap_or(base);
+    let target = format!("{}.{}{}", base, extension, if compressed { ".gz" } else { "" });
+
+    if target == config.file_path {
+        return Err(ChecklistError::Other(format!("checklist is already stored as {}", extension)));
+    }
+    if Path::new(&target).exists() {
+        return Err(ChecklistError::Other(format!("{} already exists", target)));
+    }
 
-fn add(config: Config) -> Result<(), String> {
-    // add     [task_name] [relative_start_date] [interval](optional, once)
-    
-    if config.args.len() < 2 {
-        return Err("not enough parameters".to_string());
+    let table = TaskTable::load(&config.file_path)?;
+    if config.dry_run {
+        println!("would write {}, dry run, nothing was written", target);
+        return Ok(());
     }
+    table.save(&target)?;
 
-    let checklist: Result<String, Error> = fs::read_to_string(&config.file_path);
-    let checklist: String = match checklist {
-        Ok(content) => content,
-        Err(e) => return Err(e.to_string())
+    report!(config, "wrote {}, point CHECKLIST_FILE at it to use it", target);
+
+    Ok(())
+}
+
+fn purge(mut config: Config) -> Result<(), ChecklistError> {
+    // purge   --before [date] [--yes](optional)
+    let yes = take_flag(&mut config.args, "--yes");
+    let cutoff = match take_option(&mut config.args, "--before")? {
+        Some(date) => parse_date(&date, config.today())?,
+        None => return Err(ChecklistError::NotEnoughArgs),
     };
 
-    let mut found = false;
-    for line in checklist.lines() {
-        if line.starts_with(format!("{}{}", config.args[0], ',').as_str()) {
-            found = true;
-        }
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let count = table.tasks.len();
+    table.tasks.retain(|entry| entry.due_date >= cutoff);
+    let purged = count - table.tasks.len();
+
+    if !yes {
+        say!(config, "{} tasks are due before {}, pass --yes to delete them for good", purged, cutoff);
+        return Ok(());
     }
 
-    if found {
-        return Err(format!("entry with name {} already exists", config.args[0]))
+    config.save(&table)?;
+    report!(config, "purged {} tasks due before {}", purged, cutoff);
+
+    Ok(())
+}
+
+fn clear(mut config: Config) -> Result<(), ChecklistError> {
+    // clear   [--yes](optional)
+    let yes = take_flag(&mut config.args, "--yes");
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let count = table.tasks.len();
+
+    if !yes {
+        say!(config, "the checklist has {} tasks, pass --yes to delete them all", count);
+        return Ok(());
     }
-    
-    let interval = if config.args.len() < 3 {
-        "0"
-    } else {
-        &config.args[2]
+
+    table.tasks.clear();
+    config.save(&table)?;
+    report!(config, "cleared {} tasks", count);
+
+    Ok(())
+}
+
+fn export(config: Config) -> Result<(), ChecklistError> {
+    // export  [format]
+    let format = match config.args.first() {
+        Some(format) => format.as_str(),
+        None => return Err(ChecklistError::NotEnoughArgs),
     };
-    
-    let entry = TaskEntry::deserialize(format!("{},{},{}", &config.args[0], &config.args[1], if interval == "once" {"0"} else {interval}).as_str())?;
 
-    match fs::write(config.file_path, format!("{}\n{}", entry.serialize(), checklist)) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.to_string())
+    let table = config.load_for_reading()?;
+    match format {
+        "csv" => {
+            // unlike the checklist file every row has every column, so it lines up with the header
+            println!("task_name,due_date,interval,anchor,hidden_until,url,id,target,progress,previous_due,priority,tags");
+            for entry in &table.tasks {
+                let fields = [
+                    entry.task_name.clone(),
+                    entry.due_date.to_string(),
+                    entry.interval.serialize(),
+                    entry.anchor.serialize().to_string(),
+                    entry.hidden_until.map(|date| date.to_string()).unwrap_or_default(),
+                    entry.url.clone().unwrap_or_default(),
+                    entry.id.map(|id| id.to_string()).unwrap_or_default(),
+                    entry.target.map(|target| target.to_string()).unwrap_or_default(),
+                    entry.progress.to_string(),
+                    entry.previous_due.map(|date| date.to_string()).unwrap_or_default(),
+                    entry.priority.serialize().to_string(),
+                    entry.tags.join(";"),
+                ];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                println!("{}", fields.join(","));
+            }
+        },
+        "md" => {
+            // pipes would end the cell early, backslashes are escaped so one before a pipe stays literal
+            let cell = |text: &str| text.replace('\\', "\\\\").replace('|', "\\|");
+            println!("| task | due until | interval | tags |");
+            println!("| --- | --- | --- | --- |");
+            for entry in &table.tasks {
+                println!(
+                    "| {} | {} | {} | {} |",
+                    cell(&entry.task_name),
+                    entry.due_date,
+                    entry.interval.display(),
+                    cell(&entry.tags.join(", ")),
+                );
+            }
+        },
+        _ => return Err(ChecklistError::Parse(format!("invalid export format \"{}\", expected csv or md", format))),
     }
+
+    Ok(())
 }
 
-fn remove(config: Config) -> Result<(), String> {
-    // remove  [task_name]
-    if config.args.len() < 1 {
-        return Err("not enough parameters".to_string());
+fn import(config: Config) -> Result<(), ChecklistError> {
+    // import  [file]
+    if config.args.is_empty() {
+        return Err(ChecklistError::NotEnoughArgs);
     }
-    
-    let checklist: Result<String, Error> = fs::read_to_string(&config.file_path);
-    let checklist: String = match checklist {
+
+    let content = match read_checklist(&config.args[0]) {
         Ok(content) => content,
-        Err(e) => return Err(e.to_string())
+        Err(e) => return Err(ChecklistError::io(&config.args[0], e)),
+    };
+    let (incoming, errors) = if is_json(&config.args[0]) {
+        (TaskTable::parse(&config.args[0], &content)?, vec![])
+    } else {
+        // the header row written by export csv is not a task, blanking it keeps the line numbers right
+        let content: Vec<&str> = content.lines().enumerate()
+            .map(|(i, line)| if i == 0 && line.starts_with("task_name,due_date,") { "" } else { line })
+            .collect();
+        TaskTable::deserialize_lenient(&content.join("\n"))
     };
+    if !errors.is_empty() {
+        return Err(ChecklistError::Parse(format!("{}: nothing was imported\n{}", config.args[0], errors.join("\n"))));
+    }
 
-    let mut new_checklist = String::new();
-    let mut found = false;
-    let mut first_line = true;
-    for line in checklist.lines() {
-        if !line.starts_with(format!("{}{}", config.args[0], ',').as_str()) {
-            if !first_line {
-                new_checklist.push_str("\n");
-            }
-            new_checklist.push_str(line);
-            first_line = false;
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let mut imported = vec![];
+    for entry in incoming.tasks {
+        if table.find(&entry.task_name).is_some() || imported.iter().any(|new: &TaskEntry| new.task_name == entry.task_name) {
+            report!(config, "skipped {}, a task with that name exists", entry.task_name);
+            continue;
         }
-        else {
-            found = true;
+        let id = Some(table.next_id() + imported.len() as u32);
+        imported.push(TaskEntry { id, ..entry });
+    }
+
+    config.check_max_tasks(table.tasks.len(), imported.len())?;
+
+    let count = imported.len();
+    // inserting at the top one by one would reverse them, so they keep the order of the file
+    if config.add_position == AddPosition::Top {
+        imported.reverse();
+    }
+    for entry in imported {
+        table.insert(entry, config.add_position);
+    }
+    config.save(&table)?;
+    report!(config, "imported {} tasks", count);
+
+    Ok(())
+}
+
+fn dedupe(config: Config) -> Result<(), ChecklistError> {
+    // dedupe
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let duplicates = table.dedupe();
+
+    if duplicates > 0 {
+        config.save(&table)?;
+    }
+    report!(config, "removed {} duplicate tasks", duplicates);
+
+    Ok(())
+}
+
+fn complete_all_due(mut config: Config) -> Result<(), ChecklistError> {
+    // complete-all-due [--yes](optional)
+    let yes = take_flag(&mut config.args, "--yes");
+    let today = config.today();
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let (due, rest): (Vec<TaskEntry>, Vec<TaskEntry>) = mem::take(&mut table.tasks)
+        .into_iter()
+        .partition(|entry| entry.due_date <= today && !entry.is_hidden(today));
+
+    if !yes {
+        say!(config, "{} tasks are due today or overdue, pass --yes to complete them", due.len());
+        return Ok(());
+    }
+
+    table.tasks = rest;
+    let mut completed = vec![];
+    for entry in &due {
+        if entry.interval.is_once() || config.no_recurse {
+            completed.push(format!("completed {}", entry.task_name));
+            continue;
         }
+        let rescheduled = entry.rescheduled(today)?;
+        completed.push(format!("completed {}, next due {}", entry.task_name, rescheduled.due_date));
+        table.insert(rescheduled, config.add_position);
     }
-    
-    if !found {
-        return Err(format!("cannot find task named \"{}\"", config.args[0]));
+
+    config.save(&table)?;
+    let names: Vec<&String> = due.iter().map(|entry| &entry.task_name).collect();
+    config.record_history(&names, today)?;
+    for line in &completed {
+        report!(config, "{}", line);
     }
+    report!(config, "completed {} tasks", completed.len());
 
-    if let Err(e) = fs::write(config.file_path, new_checklist) {
-        return Err(e.to_string());
+    Ok(())
+}
+
+fn history(config: Config) -> Result<(), ChecklistError> {
+    // history
+    let content = match fs::read_to_string(history_path(&config.file_path)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(ChecklistError::Io(e)),
     };
 
+    let mut rows: Vec<(String, String)> = vec![];
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        match split_csv_line(line)?.as_slice() {
+            [task_name, checked_on] => rows.push((task_name.clone(), checked_on.clone())),
+            _ => return Err(ChecklistError::Parse(format!("invalid history line \"{}\"", line))),
+        }
+    }
+
+    let width = rows.iter().map(|(task_name, _)| task_name.chars().count()).fold("task".len(), cmp::max);
+    println!("{:width$} checked on", "task", width=width);
+    println!("{}", "-".repeat(width + 1 + "checked on".len()));
+    for (task_name, checked_on) in &rows {
+        println!("{:width$} {}", task_name, checked_on, width=width);
+    }
+
     Ok(())
 }
 
-fn pop(file_path: &str, task_name: &str) -> Result<TaskEntry, String> {
-    // remove  [task_name]
-    let checklist: Result<String, Error> = fs::read_to_string(&file_path);
-    let checklist: String = match checklist {
-        Ok(content) => content,
-        Err(e) => return Err(e.to_string())
+fn forecast(mut config: Config) -> Result<(), ChecklistError> {
+    // forecast [--days number](optional, 30) [--threshold number](optional, 3)
+    let days = match take_option(&mut config.args, "--days")? {
+        Some(days) => parse_days(&days, "cannot look ahead more than")?,
+        None => 30,
+    };
+    let threshold = match take_option(&mut config.args, "--threshold")? {
+        Some(threshold) => match threshold.parse::<usize>() {
+            Ok(threshold) => threshold,
+            Err(e) => return Err(ChecklistError::Parse(format!("invalid threshold: {}", e))),
+        },
+        None => 3,
+    };
+
+    let table = config.load_for_reading()?;
+    let today = config.today();
+    let end = match today.checked_add_signed(Duration::days(days.into())) {
+        Some(end) => end,
+        None => return Err(ChecklistError::Other(format!("{} days from {} is out of range", days, today))),
     };
 
-    let mut new_checklist = String::new();
-    let mut found = false;
-    let mut first_line = true;
-    let mut entry = "";
-    for line in checklist.lines() {
-        if !line.starts_with(format!("{}{}", task_name, ',').as_str()) {
-            if !first_line {
-                new_checklist.push_str("\n");
+    // every task is assumed to be checked on the day it is due, overdue ones today
+    let mut load: Vec<Vec<&str>> = vec![vec![]; days as usize + 1];
+    for entry in &table.tasks {
+        let mut date = cmp::max(entry.due_date, today);
+        while date <= end {
+            load[(date - today).num_days() as usize].push(&entry.task_name);
+            if entry.interval.is_once() {
+                break;
             }
-            new_checklist.push_str(line);
-            first_line = false;
+            date = match entry.interval.next_due(date) {
+                Some(next) => next,
+                None => break,
+            };
         }
-        else {
-            found = true;
-            entry = line;
+    }
+
+    let mut overloaded = 0;
+    for (date, tasks) in today.iter_days().zip(&load) {
+        if tasks.len() > threshold {
+            println!("{} overloaded: {} tasks ({})", date, tasks.len(), tasks.join(", "));
+            overloaded += 1;
         }
     }
-    
-    if !found {
-        return Err(format!("cannot find task named \"{}\"", task_name));
+    if overloaded == 0 {
+        println!("no day in the next {} days has more than {} tasks", days, threshold);
     }
 
-    if let Err(e) = fs::write(file_path, new_checklist) {
-        return Err(e.to_string());
-    };
+    Ok(())
+}
 
-    Ok(TaskEntry::deserialize(entry)?)
+/// Asks `question` on stderr and returns the trimmed answer, failing once stdin is closed.
+fn prompt(question: &str) -> Result<String, ChecklistError> {
+    eprint!("{}", question);
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) => Err(ChecklistError::Other("aborted, nothing was saved".to_string())),
+        Ok(_) => Ok(answer.trim().to_string()),
+        Err(e) => Err(ChecklistError::Io(e)),
+    }
 }
 
-fn list(config: Config) -> Result<(), String> {
-    // list 
-    let checklist: String = 
-        match fs::read_to_string(&config.file_path) {
-            Ok(content) => content,
-            Err(e) => return Err(e.to_string())
-        };
-    
-    let mut length: [usize; 3] = [0; 3];
-    for line in checklist.lines() {
-        let v: Vec<&str> = line.split(',').collect();
-        for i in 0..3 {
-            length[i] = cmp::max(length[i], v[i].len());
-        }
-    }   
-    for i in 0..3 {
-        length[i] = cmp::max(length[i], ["task", "due until", "interval"][i].len());
-    }
-
-    println!(
-        "{:width1$} {:width2$} {:width3$}", 
-        "task", 
-        "due until", 
-        "interval", 
-        width1=length[0], 
-        width2=length[1], 
-        width3=length[2]
-    );
-    println!("{}", "-".repeat(length.iter().sum::<usize>() + 2));
-    let now = Local::now().date_naive();
-    for line in checklist.lines() {
-        let entry = TaskEntry::deserialize(line)?;
-        if entry.due_date < now {
-            println!("{}", entry.as_table_entry(length).red().bold());
+fn triage(config: Config) -> Result<(), ChecklistError> {
+    // triage
+    let today = config.today();
+    let mut table = TaskTable::load(&config.file_path)?;
+    let loaded = table.serialize_lines();
+    // hidden tasks are left alone until the date they were hidden until
+    let is_due = |entry: &TaskEntry| entry.due_date <= today && !entry.is_hidden(today);
+    if !table.tasks.iter().any(is_due) {
+        report!(config, "nothing is due");
+        return Ok(());
+    }
+
+    // nothing is written until every task is decided, so Ctrl-C leaves the checklist untouched
+    let mut kept = vec![];
+    let mut rescheduled = vec![];
+    let mut completed = vec![];
+    let (mut postponed, mut deleted, mut skipped) = (0, 0, 0);
+    for entry in mem::take(&mut table.tasks) {
+        if !is_due(&entry) {
+            kept.push(entry);
+            continue;
         }
-        else {
-            println!("{}", entry.as_table_entry(length));
+
+        let question = format!("{}\n[d]one, [p]ostpone, [x] delete, [s]kip? ", entry.humanized(today));
+        let choice = loop {
+            let answer = prompt(&question)?;
+            if matches!(answer.as_str(), "d" | "p" | "x" | "s") {
+                break answer;
+            }
+        };
+
+        match choice.as_str() {
+            "d" => {
+                if !entry.interval.is_once() && !config.no_recurse {
+                    rescheduled.push(entry.rescheduled(today)?);
+                }
+                completed.push(entry.task_name);
+            },
+            "p" => {
+                let due_date = loop {
+                    let answer = prompt("postpone until (tomorrow)? ")?;
+                    let answer = if answer.is_empty() { "tomorrow" } else { answer.as_str() };
+                    match parse_date(answer, today) {
+                        Ok(date) => break date,
+                        Err(e) => eprintln!("{}", e),
+                    }
+                };
+                kept.push(TaskEntry { due_date, ..entry });
+                postponed += 1;
+            },
+            "x" => deleted += 1,
+            _ => {
+                kept.push(entry);
+                skipped += 1;
+            },
         }
     }
 
+    table.tasks = kept;
+    for entry in rescheduled {
+        table.insert(entry, config.add_position);
+    }
+
+    // the lock is only taken for writing, since a lock left by Ctrl-C would block every
+    // other command, so changes made while the questions were asked are checked for instead
+    let _lock = config.lock()?;
+    if TaskTable::load(&config.file_path)?.serialize_lines() != loaded {
+        return Err(ChecklistError::Other("the checklist changed during triage, nothing was saved".to_string()));
+    }
+    config.save(&table)?;
+    let names: Vec<&String> = completed.iter().collect();
+    config.record_history(&names, today)?;
+    report!(config, "{} done, {} postponed, {} deleted, {} skipped", completed.len(), postponed, deleted, skipped);
+
     Ok(())
 }
 
-fn check(config: Config) -> Result<(), String> {
-    // check   [task_name] 
-    if config.args.len() < 1 {
-        return Err("not enough parameters".to_string());
+fn edit(config: Config) -> Result<(), ChecklistError> {
+    // edit    [task_name] [due_date] [interval](optional, unchanged)
+    if config.args.len() < 2 {
+        return Err(ChecklistError::NotEnoughArgs);
     }
 
-    let checklist: Result<String, Error> = fs::read_to_string(&config.file_path);
-    let checklist: String = match checklist {
-        Ok(content) => content,
-        Err(e) => return Err(e.to_string())
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let index = table.locate(&config.args[0])?;
+    let due_date = parse_date(&config.args[1], config.today())?;
+    let interval = match config.args.get(2) {
+        Some(interval) => Interval::parse(interval)?,
+        None => table.tasks[index].interval,
     };
 
-    let mut found = false;
-    let mut entry = TaskEntry { 
-        task_name: config.args[0].clone(), 
-        due_date: Local::now().naive_local().into(), 
-        interval: 0 
-    }; // defaults, so compiler does not complain
-    
-    for line in checklist.lines() {
-        if line.starts_with(format!("{}{}", config.args[0], ',').as_str()) {
-            found = true;
-            entry = TaskEntry::deserialize(line)?;
-        }
+    let entry = &mut table.tasks[index];
+    entry.due_date = due_date;
+    entry.interval = interval;
+    report!(config, "{}", entry.humanized(config.today()));
+
+    config.save(&table)
+}
+
+fn postpone(config: Config) -> Result<(), ChecklistError> {
+    // postpone [task_name] [days](optional, 1)
+    if config.args.is_empty() {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let days = match config.args.get(1) {
+        Some(days) => parse_days(days, "cannot postpone by more than")?,
+        None => 1,
+    };
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let index = table.locate(&config.args[0])?;
+
+    let entry = &mut table.tasks[index];
+    entry.due_date = match entry.due_date.checked_add_signed(Duration::days(days.into())) {
+        Some(due_date) => due_date,
+        None => return Err(ChecklistError::Other(format!("{} days from {} is out of range", days, entry.due_date))),
+    };
+    report!(config, "{}", entry.humanized(config.today()));
+
+    config.save(&table)
+}
+
+fn rename(config: Config) -> Result<(), ChecklistError> {
+    // rename  [old_name] [new_name]
+    if config.args.len() < 2 {
+        return Err(ChecklistError::NotEnoughArgs);
+    }
+
+    let new_name = &clean_task_name(&config.args[1])?;
+
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    let index = table.locate(&config.args[0])?;
+    if table.find(new_name).is_some() {
+        return Err(ChecklistError::DuplicateTask(new_name.clone()));
+    }
+
+    report!(config, "renamed {} to {}", table.tasks[index].task_name, new_name);
+    table.tasks[index].task_name = new_name.clone();
+
+    config.save(&table)
+}
+
+fn search(config: Config) -> Result<(), ChecklistError> {
+    // search  [term]
+    if config.args.is_empty() {
+        return Err(ChecklistError::NotEnoughArgs);
     }
 
-    if !found {
-        return Err(format!("cannot find task named \"{}\"", config.args[0]));
+    let term = config.args[0].to_lowercase();
+    let now = config.today();
+    let mut table = config.load_for_reading()?;
+    table.tasks.retain(|entry| entry.task_name.to_lowercase().contains(&term));
+    table.tasks.sort_by(|a, b| a.due_date.cmp(&b.due_date).then_with(|| a.task_name.cmp(&b.task_name)));
+
+    let headers = ["task", "due until", "interval"];
+    let length = table.column_widths(headers);
+    print_table_header(&headers, &length, 0);
+    if table.tasks.is_empty() {
+        println!("no tasks match \"{}\"", config.args[0]);
     }
+    print_table_rows(&table.tasks, length, now);
 
-    remove(config.clone())?;
+    Ok(())
+}
 
-    if entry.interval == 0 {
+fn overdue(config: Config) -> Result<(), ChecklistError> {
+    // overdue
+    let now = config.today();
+    let mut table = config.load_for_reading()?;
+    table.tasks.retain(|entry| !entry.is_hidden(now) && entry.due_date < now);
+    if table.tasks.is_empty() {
         return Ok(());
     }
+    table.tasks.sort_by(|a, b| a.due_date.cmp(&b.due_date).then_with(|| a.task_name.cmp(&b.task_name)));
+
+    let headers = ["task", "due until", "interval"];
+    let length = table.column_widths(headers);
+    print_table_header(&headers, &length, 0);
+    print_table_rows(&table.tasks, length, now);
+
+    // failing makes the exit code usable from scripts
+    Err(ChecklistError::Other(format!("{} tasks are overdue", table.tasks.len())))
+}
+
+fn upcoming(mut config: Config) -> Result<(), ChecklistError> {
+    // upcoming [days] [--include-overdue](optional)
+    let include_overdue = take_flag(&mut config.args, "--include-overdue");
+    let days = match config.args.first() {
+        Some(days) => parse_days(days, "cannot look ahead more than")?,
+        None => return Err(ChecklistError::NotEnoughArgs),
+    };
 
-    let today: NaiveDate = Local::now().naive_local().into();
-    let new_due_date = match today.checked_add_signed(Duration::days(entry.interval.into())) {
-        Some(content) => content,
-        None => return Err("could not calculate new due date".to_string())
+    let now = config.today();
+    let end = match now.checked_add_signed(Duration::days(days.into())) {
+        Some(end) => end,
+        None => return Err(ChecklistError::Other(format!("{} days from {} is out of range", days, now))),
     };
+    let mut table = config.load_for_reading()?;
+    table.tasks.retain(|entry| {
+        !entry.is_hidden(now) && entry.due_date <= end && (include_overdue || entry.due_date >= now)
+    });
+    table.tasks.sort_by(|a, b| a.due_date.cmp(&b.due_date).then_with(|| a.task_name.cmp(&b.task_name)));
 
-    add(Config {
-        file_path: config.file_path,
-        args: vec!(
-            config.args[0].clone(), // task_name
-            new_due_date.to_string(), // due_date
-            entry.interval.to_string(), // interval
-        ),
-    })?;
+    let headers = ["task", "due until", "interval"];
+    let length = table.column_widths(headers);
+    print_table_header(&headers, &length, 0);
+    print_table_rows(&table.tasks, length, now);
 
     Ok(())
 }
 
-fn uncheck(config: Config) -> Result<(), String> {
-    // uncheck [task_name]
-    if config.args.len() < 1 {
-        return Err("not enough parameters".to_string());
+fn count(config: Config) -> Result<(), ChecklistError> {
+    // count
+    let table = config.load_for_reading()?;
+    let now = config.today();
+    let visible: Vec<&TaskEntry> = table.tasks.iter().filter(|entry| !entry.is_hidden(now)).collect();
+    println!("total     {}", visible.len());
+    println!("overdue   {}", visible.iter().filter(|entry| entry.due_date < now).count());
+    println!("due today {}", visible.iter().filter(|entry| entry.due_date == now).count());
+
+    Ok(())
+}
+
+fn reindex(config: Config) -> Result<(), ChecklistError> {
+    // reindex
+    let _lock = config.lock()?;
+    let mut table = TaskTable::load(&config.file_path)?;
+    for (i, entry) in table.tasks.iter_mut().enumerate() {
+        entry.id = Some(i as u32 + 1);
+    }
+    config.save(&table)?;
+    report!(config, "numbered {} tasks", table.tasks.len());
+
+    Ok(())
+}
+
+fn bench(mut config: Config) -> Result<(), ChecklistError> {
+    // bench   [--tasks number](optional, 10000)
+    let count = match take_option(&mut config.args, "--tasks")? {
+        Some(count) => match count.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => return Err(ChecklistError::Other("number of tasks must be a positive whole number".to_string())),
+        },
+        None => 10000,
+    };
+
+    let today = config.today();
+    let checklist: Vec<String> = (0..count)
+        .map(|i| format!("task {},{},{}", i, today + Duration::days((i % 365) as i64), i % 14))
+        .collect();
+    let checklist = checklist.join("\n");
+
+    let report = |operation: &str, runs: usize, start: Instant| {
+        let elapsed = start.elapsed();
+        println!(
+            "{:6} {:>10.3} ms {:>14.0} tasks/s",
+            operation,
+            elapsed.as_secs_f64() * 1000.0,
+            (runs as f64) / elapsed.as_secs_f64(),
+        );
+    };
+
+    let start = Instant::now();
+    let mut table = TaskTable::deserialize(&checklist)?;
+    report("load", count, start);
+
+    let start = Instant::now();
+    let rendered = table.serialize();
+    report("list", rendered.lines().filter(|line| !line.is_empty()).count(), start);
+
+    let start = Instant::now();
+    for i in 0..count {
+        let entry = TaskEntry::from_args(&[format!("new {}", i), today.to_string()])?;
+        table.insert(entry, AddPosition::Sorted);
+    }
+    report("add", count, start);
+
+    let start = Instant::now();
+    for i in 0..count {
+        let name = format!("task {}", i);
+        let index = table.locate(&name)?;
+        let entry = table.tasks.remove(index);
+        if let Some(due_date) = entry.interval.next_due(today).filter(|_| !entry.interval.is_once()) {
+            table.insert(TaskEntry { due_date, ..entry }, AddPosition::Top);
+        }
     }
+    report("check", count, start);
+
+    let start = Instant::now();
+    let lines: Vec<String> = table.tasks.iter().map(|entry| entry.serialize()).collect();
+    hint::black_box(lines.join("\n"));
+    report("save", table.tasks.len(), start);
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    /// A directory of its own in the temporary directory, removed again when dropped.
+    struct TempDir {
+        path: std::path::PathBuf,
+    }
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = env::temp_dir().join(format!("checklist-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir { path }
+        }
+
+        /// Writes `content` to the file `name` in the directory, returning its path.
+        fn file(&self, name: &str, content: &str) -> String {
+            let path = self.path.join(name);
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// The configuration of `checklist <args>` on `file_path`.
+    fn config(file_path: &str, args: &[&str]) -> Config {
+        let mut args: Vec<String> = ["checklist"].iter().chain(args).map(|arg| arg.to_string()).collect();
+        args.extend(["--file".to_string(), file_path.to_string()]);
+        Config::build(args).unwrap()
+    }
+
+    #[test]
+    fn add_task_to_content() {
+        let mut checklist = Checklist::from_content("Dishes,2024-06-01,7,,,,1").unwrap();
+        let entry = TaskEntry::new("Taxes".to_string(), date("2024-07-01"), Interval::Days(0)).unwrap();
+        assert_eq!(checklist.add_task(entry).unwrap(), 2);
+        assert_eq!(checklist.content(), "Taxes,2024-07-01,0,,,,2\nDishes,2024-06-01,7,,,,1");
+    }
+
+    #[test]
+    fn add_task_rejects_duplicate_name() {
+        let mut checklist = Checklist::from_content("Dishes,2024-06-01,7").unwrap();
+        let entry = TaskEntry::new("Dishes".to_string(), date("2024-07-01"), Interval::Days(0)).unwrap();
+        assert!(matches!(checklist.add_task(entry), Err(ChecklistError::DuplicateTask(_))));
+        assert_eq!(checklist.content(), "Dishes,2024-06-01,7");
+    }
+
+    #[test]
+    fn remove_task_by_name_and_id() {
+        let mut checklist = Checklist::from_content("Dishes,2024-06-01,7,,,,1\nTaxes,2024-07-01,0,,,,2").unwrap();
+        assert_eq!(checklist.remove_task("#2").unwrap().task_name(), "Taxes");
+        assert_eq!(checklist.remove_task("Dishes").unwrap().task_name(), "Dishes");
+        assert_eq!(checklist.content(), "");
+    }
+
+    #[test]
+    fn remove_missing_task_fails() {
+        let mut checklist = Checklist::from_content("Dishes,2024-06-01,7").unwrap();
+        assert!(matches!(checklist.remove_task("Taxes"), Err(ChecklistError::TaskNotFound(_, None))));
+        assert_eq!(checklist.content(), "Dishes,2024-06-01,7");
+    }
+
+    #[test]
+    fn check_task_reschedules_recurring_task() {
+        let mut checklist = Checklist::from_content("Taxes,2024-07-01,0\nDishes,2024-06-01,7").unwrap();
+        let next = checklist.check_task("Dishes", date("2024-06-02")).unwrap().unwrap();
+        assert_eq!(next.due_date(), date("2024-06-09"));
+        assert_eq!(checklist.content(), "Dishes,2024-06-09,7,,,,,,,2024-06-01\nTaxes,2024-07-01,0");
+    }
+
+    #[test]
+    fn check_task_removes_one_time_task() {
+        let mut checklist = Checklist::from_content("Taxes,2024-07-01,0\nDishes,2024-06-01,7").unwrap();
+        assert!(checklist.check_task("Taxes", date("2024-06-02")).unwrap().is_none());
+        assert_eq!(checklist.content(), "Dishes,2024-06-01,7");
+    }
+
+    #[test]
+    fn due_rejects_too_many_days() {
+        let result = due(config("checklist.csv", &["due", "--agenda", "--days", "4000000000"]));
+        assert!(matches!(result, Err(ChecklistError::Parse(_))));
+    }
+
+    #[test]
+    fn forecast_rejects_too_many_days() {
+        let result = forecast(config("checklist.csv", &["forecast", "--days", "4000000000"]));
+        assert!(matches!(result, Err(ChecklistError::Parse(_))));
+    }
+
+    #[test]
+    fn stats_rejects_too_many_days() {
+        let result = stats(config("checklist.csv", &["stats", "--histogram", "--days", "4000000000"]));
+        assert!(matches!(result, Err(ChecklistError::Parse(_))));
+    }
+
+    #[test]
+    fn uncheck_restores_previous_due_date() {
+        let dir = TempDir::new("uncheck");
+        let path = dir.file("checklist.csv", "Water,2024-06-10,7,,,,,,,2024-06-01");
+        uncheck(config(&path, &["uncheck", "Water", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Water,2024-06-01,7");
+    }
+
+    #[test]
+    fn uncheck_fails_for_task_never_checked() {
+        let dir = TempDir::new("uncheck-unchecked");
+        let path = dir.file("checklist.csv", "Water,2024-06-10,7");
+        assert!(uncheck(config(&path, &["uncheck", "Water"])).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Water,2024-06-10,7");
+    }
+
+    #[test]
+    fn month_end_checked_late_moves_to_end_of_current_month() {
+        let entry = TaskEntry::new("Rent".to_string(), date("2026-09-30"), Interval::MonthEnd).unwrap();
+        assert_eq!(entry.rescheduled(date("2026-10-02")).unwrap().due_date(), date("2026-10-31"));
+        assert_eq!(entry.rescheduled(date("2026-09-30")).unwrap().due_date(), date("2026-10-31"));
+        assert_eq!(entry.rescheduled(date("2026-09-29")).unwrap().due_date(), date("2026-10-31"));
+    }
+
+    #[test]
+    fn month_end_business_skips_weekend() {
+        // 2024-08-31 is a Saturday and 2024-11-30 a Saturday as well
+        let entry = TaskEntry::new("Invoices".to_string(), date("2024-08-30"), Interval::MonthEndBusiness).unwrap();
+        assert_eq!(entry.rescheduled(date("2024-08-30")).unwrap().due_date(), date("2024-09-30"));
+        assert_eq!(entry.rescheduled(date("2024-11-05")).unwrap().due_date(), date("2024-11-29"));
+    }
+
+    #[test]
+    fn task_name_must_not_look_like_id() {
+        assert!(TaskEntry::new("#9".to_string(), date("2024-06-01"), Interval::Days(0)).is_err());
+        assert!(TaskEntry::new("#9 Dishes".to_string(), date("2024-06-01"), Interval::Days(0)).is_ok());
+    }
+
+    #[test]
+    fn gz_checklist_round_trips() {
+        let dir = TempDir::new("gz");
+        let path = dir.file("checklist.csv.gz", "");
+        write_checklist(&path, "Dishes,2024-06-01,7\nTaxes,2024-07-01,0").unwrap();
+        assert_eq!(&fs::read(&path).unwrap()[..2], [0x1f, 0x8b]);
+        assert_eq!(read_checklist(&path).unwrap(), "Dishes,2024-06-01,7\nTaxes,2024-07-01,0");
+    }
+
+    #[test]
+    fn check_task_reschedules_task_sharing_its_name() {
+        let mut checklist = Checklist::from_content("Dishes,2024-06-01,7\nDishes,2024-06-03,7").unwrap();
+        checklist.check_task("Dishes", date("2024-06-01")).unwrap();
+        assert_eq!(checklist.content(), "Dishes,2024-06-08,7,,,,,,,2024-06-01\nDishes,2024-06-03,7");
+    }
+
+    #[test]
+    fn reschedule_out_of_range_names_task() {
+        let entry = TaskEntry::new("Dishes".to_string(), NaiveDate::MAX, Interval::Days(7)).unwrap().with_anchor(Anchor::Due);
+        let error = entry.rescheduled(date("2024-06-01")).err().unwrap();
+        assert!(error.to_string().contains("\"Dishes\""));
+    }
+
+    #[test]
+    fn long_task_name_round_trips() {
+        let name = "x".repeat(5000);
+        let entry = TaskEntry::new(name.clone(), date("2024-06-01"), Interval::Days(7)).unwrap();
+        assert_eq!(TaskEntry::deserialize(&entry.serialize()).unwrap().task_name(), name);
+        let table = TaskTable::from_json(&TaskTable { tasks: vec![entry.clone()] }.to_json()).unwrap();
+        assert_eq!(table.tasks[0].task_name(), name);
+        assert_eq!(entry.display_name().chars().count(), MAX_DISPLAY_NAME);
+    }
+
+    #[test]
+    fn bad_intervals_are_explained() {
+        let message = |interval: &str| Interval::parse(interval).err().unwrap().to_string();
+        assert!(message("abc").starts_with("invalid interval \"abc\", interval must be a non-negative whole number of days"));
+        assert!(message("-3").starts_with("invalid interval \"-3\""));
+        assert_eq!(message("99999999"), "interval must be at most 36500 days");
+        assert_eq!(message("0w"), "interval must be at least 1w or 1mo, use once for one-time tasks");
+        assert!(message("1momo").starts_with("invalid interval \"1momo\", expected a number of weeks"));
+    }
+
+    #[test]
+    fn remove_keeps_going_past_missing_tasks() {
+        let dir = TempDir::new("remove-missing");
+        let path = dir.file("checklist.csv", "A,2024-06-01,0\nB,2024-06-01,0\nC,2024-06-01,0");
+        let error = remove(config(&path, &["remove", "A", "Missing", "B", "--quiet"])).err().unwrap();
+        assert_eq!(error.to_string(), "cannot find task named \"Missing\"");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "C,2024-06-01,0");
+    }
+
+    #[test]
+    fn dry_run_add_leaves_file_unchanged() {
+        let dir = TempDir::new("dry-run");
+        let content = "Dishes,2024-06-01,7\r\n\nTaxes,2024-07-01,0\n";
+        let path = dir.file("checklist.csv", content);
+        add(config(&path, &["add", "Laundry", "2024-06-02", "--dry-run"])).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), content.as_bytes());
+    }
+
+    #[test]
+    fn held_lock_makes_command_fail_as_busy() {
+        let dir = TempDir::new("lock");
+        let path = dir.file("checklist.csv", "Dishes,2024-06-01,7");
+        let _lock = Lock::acquire(&path).unwrap();
+        let error = add(config(&path, &["add", "Laundry", "2024-06-02"])).err().unwrap();
+        assert!(error.to_string().starts_with("checklist is busy"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Dishes,2024-06-01,7");
+    }
+
+    #[test]
+    fn clear_and_purge_need_yes() {
+        let dir = TempDir::new("clear");
+        let content = "Dishes,2024-06-01,7\nTaxes,2024-07-01,0";
+        let path = dir.file("checklist.csv", content);
+        clear(config(&path, &["clear"])).unwrap();
+        purge(config(&path, &["purge", "--before", "2024-06-15"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        purge(config(&path, &["purge", "--before", "2024-06-15", "--yes", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Taxes,2024-07-01,0");
+        clear(config(&path, &["clear", "--yes", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn import_skips_duplicate_and_prepends_new_task() {
+        let dir = TempDir::new("import");
+        let path = dir.file("checklist.csv", "Dishes,2024-06-01,7");
+        let incoming = dir.file("incoming.csv", "Dishes,2024-07-01,1\nTaxes,2024-07-01,0");
+        import(config(&path, &["import", &incoming, "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Taxes,2024-07-01,0,,,,1\nDishes,2024-06-01,7");
+    }
+
+    #[test]
+    fn import_refuses_file_with_malformed_line() {
+        let dir = TempDir::new("import-malformed");
+        let path = dir.file("checklist.csv", "Dishes,2024-06-01,7");
+        let incoming = dir.file("incoming.csv", "Taxes,2024-07-01,0\nLaundry,someday,0");
+        let error = import(config(&path, &["import", &incoming])).err().unwrap();
+        assert!(error.to_string().contains("line 2: "));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Dishes,2024-06-01,7");
+    }
+
+    #[test]
+    fn relative_due_text() {
+        let today = date("2024-06-01");
+        let relative = |due: &str| TaskEntry::new("Dishes".to_string(), date(due), Interval::Days(0)).unwrap().relative_due(today);
+        assert_eq!(relative("2024-05-29"), "3 days ago");
+        assert_eq!(relative("2024-05-31"), "1 day ago");
+        assert_eq!(relative("2024-06-01"), "today");
+        assert_eq!(relative("2024-06-02"), "in 1 day");
+        assert_eq!(relative("2024-06-11"), "in 10 days");
+    }
+
+    #[test]
+    fn empty_and_padded_task_names() {
+        let entry = |name: &str| TaskEntry::new(name.to_string(), date("2024-06-01"), Interval::Days(0));
+        assert!(matches!(entry(""), Err(ChecklistError::Parse(_))));
+        assert!(matches!(entry(" \t "), Err(ChecklistError::Parse(_))));
+        assert_eq!(entry("  Dishes ").unwrap().task_name(), "Dishes");
+    }
+
+    #[test]
+    fn status_code_returns_overdue_count() {
+        let dir = TempDir::new("status");
+        let path = dir.file("checklist.csv", "A,2024-05-01,0\nB,2024-05-02,0\nC,2024-07-01,0");
+        let result = status(config(&path, &["status", "--code", "--assume-today", "2024-06-01"]));
+        assert!(matches!(result, Err(ChecklistError::Exit(2))));
+        let result = status(config(&path, &["status", "--code", "--assume-today", "2024-04-01"]));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn complete_all_due_leaves_hidden_tasks() {
+        let dir = TempDir::new("complete-all-due");
+        let path = dir.file("checklist.csv", "Dishes,2024-06-01,0\nTaxes,2024-05-01,0,,2024-06-10");
+        complete_all_due(config(&path, &["complete-all-due", "--yes", "--quiet", "--assume-today", "2024-06-01"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Taxes,2024-05-01,0,,2024-06-10");
+    }
+
+    #[test]
+    fn hide_accepts_date_words_and_task_named_until() {
+        let dir = TempDir::new("hide");
+        let path = dir.file("checklist.csv", "until,2024-06-01,0");
+        // 2024-06-01 is a Saturday
+        hide(config(&path, &["hide", "until", "until", "friday", "--assume-today", "2024-06-01"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "until,2024-06-01,0,,2024-06-07");
+    }
+
+    #[test]
+    fn json_checklist_loads_without_csv_round_trip() {
+        let dir = TempDir::new("json");
+        let path = dir.file("checklist.json", r#"[{"task_name": "Milk, \"eggs\"", "due_date": "2024-06-01", "interval": 7, "tags": ["home"]}]"#);
+        let table = TaskTable::load(&path).unwrap();
+        assert_eq!(table.tasks[0].task_name(), "Milk, \"eggs\"");
+        assert_eq!(table.tasks[0].tags(), ["home"]);
+        table.save(&path).unwrap();
+        assert_eq!(TaskTable::load(&path).unwrap().serialize_lines(), table.serialize_lines());
+    }
+
+    #[test]
+    fn json_checklist_rejects_line_breaks_and_semicolons() {
+        let dir = TempDir::new("json-invalid");
+        let path = dir.file("checklist.json", r#"[{"task_name": "Milk\neggs", "due_date": "2024-06-01"}]"#);
+        let error = TaskTable::load(&path).err().unwrap().to_string();
+        assert_eq!(error, format!("{}: task name must not contain line breaks", path));
+        let path = dir.file("checklist.json", r#"[{"task_name": "Milk", "due_date": "2024-06-01", "tags": ["a;b"]}]"#);
+        assert!(TaskTable::load(&path).is_err());
+    }
+
+    #[test]
+    fn late_check_follows_anchor() {
+        let mut checklist = Checklist::from_content("Water,2024-06-01,7,last\nRent,2024-06-01,7,due").unwrap();
+        let water = checklist.check_task("Water", date("2024-06-05")).unwrap().unwrap();
+        let rent = checklist.check_task("Rent", date("2024-06-05")).unwrap().unwrap();
+        assert_eq!(water.due_date(), date("2024-06-12"));
+        assert_eq!(rent.due_date(), date("2024-06-08"));
+    }
+
+    #[test]
+    fn edit_due_date_keeps_interval_and_position() {
+        let dir = TempDir::new("edit");
+        let path = dir.file("checklist.csv", "Dishes,2024-06-01,7\nTaxes,2024-07-01,0\nLaundry,2024-06-03,14");
+        edit(config(&path, &["edit", "Taxes", "2024-08-01", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Dishes,2024-06-01,7\nTaxes,2024-08-01,0\nLaundry,2024-06-03,14");
+
+        edit(config(&path, &["edit", "Laundry", "+3", "--assume-today", "2024-06-01", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Dishes,2024-06-01,7\nTaxes,2024-08-01,0\nLaundry,2024-06-04,14");
+
+        let error = edit(config(&path, &["edit", "Missing", "2024-08-01"])).err().unwrap();
+        assert_eq!(error.to_string(), "cannot find task named \"Missing\"");
+    }
+
+    #[test]
+    fn humanized_due_dates_and_intervals() {
+        let today = date("2024-06-01");
+        let humanized = |due_date: &str, interval: Interval| {
+            TaskEntry::new("Dishes".to_string(), date(due_date), interval).unwrap().humanized(today)
+        };
+        assert_eq!(humanized("2024-05-29", Interval::Days(0)), "Dishes — overdue by 3 days (once)");
+        assert_eq!(humanized("2024-05-31", Interval::Days(1)), "Dishes — overdue by 1 day (every day)");
+        assert_eq!(humanized("2024-06-01", Interval::Days(3)), "Dishes — due today (every 3 days)");
+        assert_eq!(humanized("2024-06-02", Interval::Weeks(1)), "Dishes — due in 1 day (every week)");
+        assert_eq!(humanized("2024-06-05", Interval::Weeks(2)), "Dishes — due in 4 days (every 2 weeks)");
+        assert_eq!(humanized("2024-06-01", Interval::Months(1)), "Dishes — due today (every month)");
+        assert_eq!(humanized("2024-06-01", Interval::Months(3)), "Dishes — due today (every 3 months)");
+        assert_eq!(humanized("2024-06-03", Interval::Weekday(Weekday::Mon)), "Dishes — due in 2 days (every Monday)");
+        assert_eq!(humanized("2024-06-30", Interval::MonthEnd), "Dishes — due in 29 days (every month end)");
+        assert_eq!(humanized("2024-06-28", Interval::MonthEndBusiness), "Dishes — due in 27 days (every last weekday of the month)");
+    }
+
+    #[test]
+    fn name_lookup_matches_whole_names() {
+        let dir = TempDir::new("whole-names");
+        let path = dir.file("checklist.csv", "a,2024-06-01,7\n\"a,b\",2024-06-01,7");
+        check(config(&path, &["check", "a", "--assume-today", "2024-06-01", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a,2024-06-08,7,,,,,,,2024-06-01\n\"a,b\",2024-06-01,7");
+        remove(config(&path, &["remove", "a", "--quiet"])).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\"a,b\",2024-06-01,7");
+        add(config(&path, &["add", "a", "2024-06-02", "--quiet"])).unwrap();
+        assert!(add(config(&path, &["add", "a", "2024-06-02"])).is_err());
+    }
+
+    #[test]
+    fn from_content_rejects_malformed_line() {
+        assert!(matches!(Checklist::from_content("Dishes,2024-06-01"), Err(ChecklistError::Parse(_))));
+    }
+}
@@ -1,21 +1,42 @@
 use std::process;
 use std::env;
+use std::io::{self, IsTerminal};
 
-use checklist::Config;
+use checklist::{ChecklistError, Config};
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() < 2 {
-        eprintln!("no command given");
-        process::exit(1);
+    let mut args: Vec<String> = env::args().collect();
+
+    // global options may come before the command, which Config::build expects right after the program name
+    match checklist::command_position(&args) {
+        Some(index) => {
+            let command = args.remove(index);
+            args.insert(1, command);
+        },
+        // without a command, fall back to CHECKLIST_DEFAULT_CMD, or to list when run interactively
+        None => match env::var("CHECKLIST_DEFAULT_CMD") {
+            Ok(command) => {
+                let words: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+                if words.is_empty() {
+                    eprintln!("CHECKLIST_DEFAULT_CMD is empty");
+                    process::exit(1);
+                }
+                args.splice(1..1, words);
+            },
+            Err(_) if io::stdout().is_terminal() => args.insert(1, "list".to_string()),
+            Err(_) => {
+                eprintln!("no command given");
+                process::exit(1);
+            }
+        },
     }
 
-    let command = checklist::parse_command(&args[1]);
-
-    let command = match command {
+    let command = match checklist::parse_command(&args[1]) {
         Ok(com) => com,
-        Err(msg) => panic!("{msg}"),
+        Err(msg) => {
+            eprintln!("{msg}");
+            process::exit(1);
+        },
     };
 
     let config = Config::build(args).unwrap_or_else(|err| {
@@ -23,9 +44,16 @@ fn main() {
         process::exit(1);
     });
 
-    if let Err(msg) = command(config) {
-        eprintln!("{msg}");
-        process::exit(1);
+    config.apply_color();
+    config.print_summary();
+
+    match command(config) {
+        Ok(()) => {},
+        Err(ChecklistError::Exit(code)) => process::exit(code.into()),
+        Err(msg) => {
+            eprintln!("{msg}");
+            process::exit(1);
+        },
     }
 }
 
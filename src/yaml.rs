@@ -0,0 +1,18 @@
+/// Formats tasks given as already JSON encoded fields as a YAML sequence of mappings.
+///
+/// JSON strings, numbers, booleans and null are valid YAML flow scalars, so the values
+/// are written as they are, which also keeps names with quotes or newlines intact.
+pub fn sequence(items: &[Vec<(&str, String)>]) -> String {
+    if items.is_empty() {
+        return "[]".to_string();
+    }
+
+    let mut yaml = String::new();
+    for fields in items {
+        for (i, (key, value)) in fields.iter().enumerate() {
+            let indent = if i == 0 { "- " } else { "  " };
+            yaml.push_str(&format!("{}{}: {}\n", indent, key, value));
+        }
+    }
+    yaml.trim_end().to_string()
+}
@@ -0,0 +1,329 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// A checklist file in its own temporary directory, removed again when dropped.
+struct Fixture {
+    directory: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str, content: &str) -> Fixture {
+        let directory = std::env::temp_dir().join(format!("checklist-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("checklist.csv"), content).unwrap();
+        Fixture { directory }
+    }
+
+    fn path(&self) -> String {
+        self.directory.join("checklist.csv").to_string_lossy().into_owned()
+    }
+
+    fn content(&self) -> String {
+        fs::read_to_string(self.path()).unwrap()
+    }
+
+    /// The path of the file `name` next to the checklist.
+    fn sibling(&self, name: &str) -> PathBuf {
+        self.directory.join(name)
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        checklist(&self.path(), args, "")
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// Runs the checklist binary on `file_path` on 2024-06-01 with `stdin` piped in.
+fn checklist(file_path: &str, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_checklist"))
+        .args(args)
+        .args(["--file", file_path, "--assume-today", "2024-06-01", "--color", "never"])
+        .env_remove("CHECKLIST_FILE")
+        .env_remove("CHECKLIST_SHOW_SUMMARY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn add_confirms_on_stdout() {
+    let fixture = Fixture::new("add", "");
+    let output = fixture.run(&["add", "Buy milk", "2024-06-03"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "added \"Buy milk\" due 2024-06-03\n");
+}
+
+#[test]
+fn remove_confirms_on_stdout() {
+    let fixture = Fixture::new("remove", "Taxes,2024-06-01,0");
+    let output = fixture.run(&["remove", "Taxes"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "removed Taxes — due today (once)\n");
+}
+
+#[test]
+fn check_confirms_next_due_date_of_recurring_task() {
+    let fixture = Fixture::new("check", "Dishes,2024-06-01,7\nTaxes,2024-06-01,0");
+    let output = fixture.run(&["check", "Dishes", "Taxes"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "checked \"Dishes\", next due 2024-06-08\nchecked \"Taxes\"\n");
+}
+
+#[test]
+fn quiet_silences_confirmations() {
+    let fixture = Fixture::new("quiet", "Dishes,2024-06-01,7");
+    for args in [&["add", "Taxes", "today"][..], &["check", "Dishes"], &["remove", "Taxes"]] {
+        let output = fixture.run(&[args, &["--quiet"]].concat());
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "");
+    }
+}
+
+#[test]
+fn dry_run_prints_no_confirmation() {
+    let fixture = Fixture::new("dry-run", "");
+    let output = fixture.run(&["add", "Q", "today", "--dry-run"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "+ Q,2024-06-01,0,,,,1\ndry run, nothing was written\n");
+    assert_eq!(fixture.content(), "");
+}
+
+#[test]
+fn piped_checklist_keeps_confirmations_off_stdout() {
+    let output = checklist("-", &["check", "Dishes"], "Dishes,2024-01-01,7\nTaxes,2024-01-01,0\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Dishes,2024-06-08,7,,,,,,,2024-01-01\nTaxes,2024-01-01,0\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "checked \"Dishes\", next due 2024-06-08\n");
+}
+
+#[test]
+fn piped_checklist_is_passed_on_when_nothing_changes() {
+    let content = "Dishes,2024-01-01,7\nTaxes,2024-01-01,0\n";
+    for args in [&["dedupe"][..], &["ensure", "Dishes", "2024-01-01", "7"], &["add", "Dishes", "2024-01-01", "--replace-if-newer"]] {
+        let output = checklist("-", args, content);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), content);
+    }
+
+    let output = checklist("-", &["remove", "Laundry"], content);
+    assert!(!output.status.success());
+    assert_eq!(stdout(&output), content);
+}
+
+#[test]
+fn task_name_with_commas_survives_add_list_remove() {
+    let fixture = Fixture::new("commas", "");
+    assert!(fixture.run(&["add", "Buy milk, eggs, bread", "today", "--quiet"]).status.success());
+    assert_eq!(fixture.content(), "\"Buy milk, eggs, bread\",2024-06-01,0,,,,1");
+
+    let output = fixture.run(&["list"]);
+    assert!(stdout(&output).contains("Buy milk, eggs, bread 2024-06-01"));
+
+    assert!(fixture.run(&["remove", "Buy milk, eggs, bread"]).status.success());
+    assert_eq!(fixture.content(), "");
+}
+
+/// The task names of the rows `list` printed, in order.
+fn listed_names(output: &Output) -> Vec<String> {
+    stdout(output).lines().skip(2).map(|line| line.split(' ').next().unwrap().to_string()).collect()
+}
+
+#[test]
+fn list_sorts_by_due_date_regardless_of_file_order() {
+    let fixture = Fixture::new("list-order", "Later,2024-06-09,0\nFirst,2024-05-01,0\nMiddle,2024-06-01,0");
+    assert_eq!(listed_names(&fixture.run(&["list"])), ["First", "Middle", "Later"]);
+    assert_eq!(fixture.content(), "Later,2024-06-09,0\nFirst,2024-05-01,0\nMiddle,2024-06-01,0");
+}
+
+#[test]
+fn list_sort_keys() {
+    let fixture = Fixture::new("list-sort", "Beta,2024-06-03,1\nAlpha,2024-06-05,0\nGamma,2024-06-01,14");
+    assert_eq!(listed_names(&fixture.run(&["list", "--sort", "due"])), ["Gamma", "Beta", "Alpha"]);
+    assert_eq!(listed_names(&fixture.run(&["list", "--sort", "name"])), ["Alpha", "Beta", "Gamma"]);
+    assert_eq!(listed_names(&fixture.run(&["list", "--sort", "interval"])), ["Beta", "Gamma", "Alpha"]);
+    assert_eq!(listed_names(&fixture.run(&["list", "--sort", "name", "--reverse"])), ["Gamma", "Beta", "Alpha"]);
+    assert!(!fixture.run(&["list", "--sort", "size"]).status.success());
+}
+
+#[test]
+fn named_lists_write_to_their_own_files() {
+    let fixture = Fixture::new("lists", "");
+    fs::write(fixture.sibling("work.csv"), "").unwrap();
+    fs::write(fixture.sibling("home.csv"), "").unwrap();
+    assert!(fixture.run(&["add", "Report", "today", "--list", "work.csv"]).status.success());
+    assert!(fixture.run(&["add", "Dishes", "today", "--list", "home.csv"]).status.success());
+    assert_eq!(fs::read_to_string(fixture.sibling("work.csv")).unwrap(), "Report,2024-06-01,0,,,,1");
+    assert_eq!(fs::read_to_string(fixture.sibling("home.csv")).unwrap(), "Dishes,2024-06-01,0,,,,1");
+    assert_eq!(fixture.content(), "");
+}
+
+#[test]
+fn search_ignores_case() {
+    let fixture = Fixture::new("search", "Water plants,2024-06-02,7\nDishes,2024-06-01,1\nwatch film,2024-06-03,0");
+    let output = fixture.run(&["search", "WAT"]);
+    assert!(output.status.success());
+    let rows: Vec<String> = stdout(&output).lines().skip(2).map(|line| line.trim_end().to_string()).collect();
+    assert_eq!(rows, ["Water plants 2024-06-02 7", "watch film   2024-06-03 once"]);
+
+    let output = fixture.run(&["search", "laundry"]);
+    assert!(stdout(&output).ends_with("no tasks match \"laundry\"\n"));
+}
+
+#[test]
+fn export_matches_fixtures() {
+    let fixture = Fixture::new("export", "\"Milk, eggs\",2024-06-01,7,,,,1\nPipe | name,2024-06-02,0,,,,2,,,,high,home;errand");
+
+    let output = fixture.run(&["export", "csv"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), concat!(
+        "task_name,due_date,interval,anchor,hidden_until,url,id,target,progress,previous_due,priority,tags\n",
+        "\"Milk, eggs\",2024-06-01,7,last,,,1,,0,,medium,\n",
+        "Pipe | name,2024-06-02,0,last,,,2,,0,,high,home;errand\n",
+    ));
+
+    let output = fixture.run(&["export", "md"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), concat!(
+        "| task | due until | interval | tags |\n",
+        "| --- | --- | --- | --- |\n",
+        "| Milk, eggs | 2024-06-01 | 7 |  |\n",
+        "| Pipe \\| name | 2024-06-02 | once | home, errand |\n",
+    ));
+}
+
+#[test]
+fn upcoming_window() {
+    let fixture = Fixture::new("upcoming", "Past,2024-05-30,0\nToday,2024-06-01,0\nSoon,2024-06-04,0\nLater,2024-07-01,0");
+    assert_eq!(listed_names(&fixture.run(&["upcoming", "0"])), ["Today"]);
+    assert_eq!(listed_names(&fixture.run(&["upcoming", "3"])), ["Today", "Soon"]);
+    assert_eq!(listed_names(&fixture.run(&["upcoming", "3", "--include-overdue"])), ["Past", "Today", "Soon"]);
+}
+
+#[test]
+fn file_flag_takes_precedence_over_environment() {
+    let fixture = Fixture::new("file-flag", "Flag,2024-06-01,0");
+    let env_path = fixture.sibling("env.csv");
+    fs::write(&env_path, "Env,2024-06-01,0").unwrap();
+    let search = |args: &[&str], env: Option<&PathBuf>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_checklist"));
+        command.arg("search").arg("").args(args).env_remove("CHECKLIST_FILE");
+        if let Some(env) = env {
+            command.env("CHECKLIST_FILE", env);
+        }
+        command.output().unwrap()
+    };
+
+    let output = search(&["--file", &fixture.path()], Some(&env_path));
+    assert!(stdout(&output).contains("Flag"));
+    let output = search(&[], Some(&env_path));
+    assert!(stdout(&output).contains("Env"));
+    let output = search(&[], None);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("no checklist file, pass --file or set CHECKLIST_FILE"));
+}
+
+#[test]
+fn list_json_marks_overdue_tasks() {
+    let fixture = Fixture::new("json", "Late,2024-05-31,0\nToday,2024-06-01,0");
+    let output = stdout(&fixture.run(&["list", "--json"]));
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[1].ends_with("\"overdue\": true},"));
+    assert!(lines[2].ends_with("\"overdue\": false}"));
+    assert!(!output.contains("days_until_due"));
+
+    let output = stdout(&fixture.run(&["list", "--json-computed"]));
+    assert!(output.contains("\"days_until_due\": -1, \"overdue\": true}"));
+}
+
+#[test]
+fn triage_leaves_hidden_tasks() {
+    let fixture = Fixture::new("triage", "Dishes,2024-06-01,0\nTaxes,2024-05-01,0,,2024-06-10");
+    let output = checklist(&fixture.path(), &["triage", "--quiet"], "d\n");
+    assert!(output.status.success());
+    assert_eq!(fixture.content(), "Taxes,2024-05-01,0,,2024-06-10");
+}
+
+#[test]
+fn ensure_keeps_everything_but_the_schedule() {
+    let line = "Dishes,2024-06-01,7,due,2024-05-20,https://example.com,1,,,,high,home";
+    let fixture = Fixture::new("ensure", line);
+    let output = fixture.run(&["ensure", "Dishes", "2024-06-01", "7"]);
+    assert_eq!(stdout(&output), "unchanged\n");
+    assert_eq!(fixture.content(), line);
+
+    let output = fixture.run(&["ensure", "Dishes", "2024-06-03", "14"]);
+    assert_eq!(stdout(&output), "updated\n");
+    assert_eq!(fixture.content(), "Dishes,2024-06-03,14,due,2024-05-20,https://example.com,1,,,,high,home");
+
+    assert!(fixture.run(&["ensure", "Dishes", "2024-06-03", "14", "--anchor", "last"]).status.success());
+    assert_eq!(fixture.content(), "Dishes,2024-06-03,14,,2024-05-20,https://example.com,1,,,,high,home");
+}
+
+#[test]
+fn global_flags_may_precede_the_command() {
+    let fixture = Fixture::new("global-flags", "");
+    let output = fixture.run(&["--quiet", "--no-recurse", "add", "Taxes", "today"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(fixture.content(), "Taxes,2024-06-01,0,,,,1");
+
+    let output = fixture.run(&["--quiet", "frobnicate"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!String::from_utf8(output.stderr).unwrap().contains("panicked"));
+}
+
+#[test]
+fn default_command_is_split_into_words() {
+    let fixture = Fixture::new("default-cmd", "Taxes,2024-06-01,0");
+    let output = Command::new(env!("CARGO_BIN_EXE_checklist"))
+        .args(["--quiet", "--file", &fixture.path(), "--assume-today", "2024-06-01"])
+        .env("CHECKLIST_DEFAULT_CMD", "list --json")
+        .env_remove("CHECKLIST_SHOW_SUMMARY")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("[\n  {\"task_name\": \"Taxes\""));
+}
+
+#[test]
+fn status_and_count_leave_out_hidden_tasks() {
+    let fixture = Fixture::new("hidden-counts", "Dishes,2024-05-31,0\nTaxes,2024-05-01,0,,2024-06-10");
+    assert_eq!(stdout(&fixture.run(&["status"])), "1 overdue, 1 total\n");
+    assert_eq!(fixture.run(&["status", "--code"]).status.code(), Some(1));
+    assert_eq!(stdout(&fixture.run(&["count"])), "total     1\noverdue   1\ndue today 0\n");
+    for seed in ["1", "2", "3"] {
+        assert!(stdout(&fixture.run(&["random", "--seed", seed])).starts_with("Dishes"));
+    }
+}
+
+#[test]
+fn count_by_due_date() {
+    let fixture = Fixture::new("count", "Late,2024-05-30,0\nToday,2024-06-01,7\nAlso today,2024-06-01,0\nLater,2024-06-09,0");
+    assert_eq!(stdout(&fixture.run(&["count"])), "total     4\noverdue   1\ndue today 2\n");
+
+    let fixture = Fixture::new("count-empty", "");
+    assert_eq!(stdout(&fixture.run(&["count"])), "total     0\noverdue   0\ndue today 0\n");
+}
+
+#[test]
+fn search_folds_unicode_case() {
+    let fixture = Fixture::new("search-unicode", "Çöp,2024-06-01,7\nDishes,2024-06-01,1");
+    assert_eq!(listed_names(&fixture.run(&["search", "çöp"])), ["Çöp"]);
+    assert_eq!(listed_names(&fixture.run(&["search", "ÇÖP"])), ["Çöp"]);
+}